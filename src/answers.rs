@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use crate::solver::{Answer, Part, SolveError};
+
+const HEADER: &str = "# Answers accepted by adventofcode.com, one per line: <year> <day> <part> <answer>";
+
+/// AnswerLedger holds the answers that were accepted by the puzzle website. They are stored one
+/// per line as `<year> <day> <part> <answer>`, and lines starting with `#` are ignored. Answers
+/// that span several lines are stored with their line breaks escaped as `\n`.
+#[derive(Debug, Default)]
+pub struct AnswerLedger {
+    entries: BTreeMap<(usize, u8, Part), String>,
+}
+
+impl AnswerLedger {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<AnswerLedger> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(contents: &str) -> io::Result<AnswerLedger> {
+        let mut ledger = AnswerLedger::default();
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected `<year> <day> <part> <answer>`, found {line:?}", idx + 1),
+                )
+            };
+            let fields = line.splitn(4, ' ').collect::<Vec<_>>();
+            let [year, day, part, answer] = fields[..] else {
+                return Err(invalid());
+            };
+            let year = year.parse().map_err(|_| invalid())?;
+            let day = day.parse().map_err(|_| invalid())?;
+            let part = match part {
+                "1" => Part::One,
+                "2" => Part::Two,
+                _ => return Err(invalid()),
+            };
+            ledger.entries.insert((year, day, part), answer.replace("\\n", "\n"));
+        }
+        Ok(ledger)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn get(&self, year: usize, day: u8, part: Part) -> Option<&str> {
+        self.entries.get(&(year, day, part)).map(|answer| answer.as_str())
+    }
+
+    /// Records an answer unless the part already has one. Returns whether it was recorded.
+    pub fn record(&mut self, year: usize, day: u8, part: Part, answer: &Answer) -> bool {
+        if self.entries.contains_key(&(year, day, part)) {
+            return false;
+        }
+        self.entries.insert((year, day, part), answer.to_string());
+        true
+    }
+
+    /// Compares what a solver returned for a part against the recorded answer.
+    pub fn check(&self, year: usize, day: u8, part: Part, result: &Result<Answer, SolveError>) -> Status {
+        match (result, self.get(year, day, part)) {
+            (Err(err), _) => Status::Error(err.clone()),
+            (Ok(answer), None) => Status::Missing { actual: answer.clone() },
+            (Ok(answer), Some(expected)) if *answer == expected => Status::Pass,
+            (Ok(answer), Some(expected)) => Status::Fail {
+                expected: expected.to_string(),
+                actual: answer.clone(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for AnswerLedger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        for ((year, day, part), answer) in self.entries.iter() {
+            writeln!(f, "{year} {day} {part} {}", answer.replace('\n', "\\n"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Status is the outcome of checking one part against the ledger.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Pass,
+    Fail { expected: String, actual: Answer },
+    /// The solver produced an answer, but none has been recorded for the part yet.
+    Missing { actual: Answer },
+    Error(SolveError),
+}
+
+impl Status {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Status::Fail { .. } | Status::Error(_))
+    }
+
+    /// A short label for the verification table.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Fail { .. } => "FAIL",
+            Status::Missing { .. } => "missing",
+            Status::Error(_) => "ERROR",
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "pass"),
+            Status::Fail { expected, actual } => write!(f, "expected {expected}, got {actual}"),
+            Status::Missing { actual } => write!(f, "no recorded answer, got {actual}"),
+            Status::Error(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::DEFAULT_ANSWERS_PATH;
+    use crate::registry::SolverRegistry;
+    use crate::test_support::personal_input;
+
+    #[test]
+    fn test_parse_ledger() {
+        let ledger = AnswerLedger::parse("# comment\n2023 5 1 551761867\n\n2023 5 2 57451709\n").unwrap();
+        assert_eq!(ledger.get(2023, 5, Part::One), Some("551761867"));
+        assert_eq!(ledger.get(2023, 5, Part::Two), Some("57451709"));
+        assert_eq!(ledger.get(2023, 6, Part::One), None);
+        let err = AnswerLedger::parse("2023 5 3 1").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: expected `<year> <day> <part> <answer>`, found \"2023 5 3 1\"");
+    }
+
+    #[test]
+    fn test_record_and_round_trip() {
+        let mut ledger = AnswerLedger::parse("2023 5 1 35\n").unwrap();
+        assert!(!ledger.record(2023, 5, Part::One, &Answer::from(36usize)));
+        assert!(ledger.record(2023, 5, Part::Two, &Answer::from(46usize)));
+        let grid = Answer::Grid(vec!["#.".to_string(), ".#".to_string()]);
+        assert!(ledger.record(2022, 10, Part::Two, &grid));
+
+        let text = ledger.to_string();
+        assert_eq!(text, format!("{HEADER}\n2022 10 2 #.\\n.#\n2023 5 1 35\n2023 5 2 46\n"));
+        let reloaded = AnswerLedger::parse(&text).unwrap();
+        assert_eq!(reloaded.get(2022, 10, Part::Two), Some("#.\n.#"));
+        assert_eq!(reloaded.check(2022, 10, Part::Two, &Ok(grid)), Status::Pass);
+    }
+
+    #[test]
+    fn test_check() {
+        let ledger = AnswerLedger::parse("2023 5 1 35\n").unwrap();
+        assert_eq!(ledger.check(2023, 5, Part::One, &Ok(Answer::from(35usize))), Status::Pass);
+        let status = ledger.check(2023, 5, Part::One, &Ok(Answer::from(36usize)));
+        assert_eq!(status.to_string(), "expected 35, got 36");
+        assert!(status.is_failure());
+        let status = ledger.check(2023, 5, Part::Two, &Ok(Answer::from(46usize)));
+        assert_eq!(status.label(), "missing");
+        assert!(!status.is_failure());
+        let status = ledger.check(2023, 5, Part::Two, &Err(SolveError::new("no seeds")));
+        assert!(status.is_failure());
+    }
+
+    #[test]
+    fn test_registered_solvers_match_ledger() {
+        let ledger = AnswerLedger::load(Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_ANSWERS_PATH)).unwrap();
+        let registry = SolverRegistry::with_all_years();
+        for (year, day) in registry.entries() {
+            let Some(input) = personal_input(year, day) else {
+                continue;
+            };
+            let run = registry.get(year, day).unwrap().run(&input, &Part::ALL).unwrap();
+            for part_run in run.parts {
+                let status = ledger.check(year, day, part_run.part, &part_run.answer);
+                assert_eq!(status, Status::Pass, "{year} day {day} part {}", part_run.part);
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+use crate::solver::{DynSolver, Part, SolveError};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BenchConfig {
+    /// Number of timed runs.
+    pub iterations: usize,
+    /// Number of runs thrown away before timing starts, so caches and the allocator are warm.
+    pub warmup: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            iterations: 10,
+            warmup: 2,
+        }
+    }
+}
+
+/// Stats summarises the timings of one phase over every timed iteration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stats {
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+    pub stddev: Duration,
+}
+
+impl Stats {
+    pub fn from_samples(samples: &[Duration]) -> Stats {
+        assert!(!samples.is_empty(), "stats need at least one sample");
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let median = if n % 2 == 1 {
+            sorted[n / 2]
+        } else {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2
+        };
+        let mean_ns = sorted.iter().map(|d| d.as_nanos() as f64).sum::<f64>() / n as f64;
+        // Population standard deviation, every iteration is a sample of the same run.
+        let variance = sorted
+            .iter()
+            .map(|d| (d.as_nanos() as f64 - mean_ns).powi(2))
+            .sum::<f64>() / n as f64;
+        Stats {
+            min: sorted[0],
+            median,
+            mean: Duration::from_nanos(mean_ns.round() as u64),
+            stddev: Duration::from_nanos(variance.sqrt().round() as u64),
+        }
+    }
+
+    fn to_json(self) -> String {
+        format!(
+            "{{\"min_ns\": {}, \"median_ns\": {}, \"mean_ns\": {}, \"stddev_ns\": {}}}",
+            self.min.as_nanos(),
+            self.median.as_nanos(),
+            self.mean.as_nanos(),
+            self.stddev.as_nanos(),
+        )
+    }
+}
+
+/// BenchResult holds the statistics of parsing and solving each part of a single day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub year: usize,
+    pub day: u8,
+    pub iterations: usize,
+    pub parse: Stats,
+    pub part_1: Stats,
+    pub part_2: Stats,
+}
+
+/// Runs the solver `config.warmup + config.iterations` times on the input and collects the parse
+/// and per-part timings of the timed iterations. Fails as soon as a part returns an error, since
+/// timing a failure isn't meaningful.
+pub fn bench_day(
+    solver: &dyn DynSolver,
+    year: usize,
+    day: u8,
+    input: &str,
+    config: BenchConfig,
+) -> Result<BenchResult, SolveError> {
+    let mut parse = vec![];
+    let mut parts = [vec![], vec![]];
+    for iteration in 0..config.warmup + config.iterations {
+        let run = solver.run(input, &Part::ALL)?;
+        for part_run in run.parts.iter() {
+            if let Err(err) = &part_run.answer {
+                return Err(err.clone());
+            }
+        }
+        if iteration < config.warmup {
+            continue;
+        }
+        parse.push(run.parse_time);
+        for part_run in run.parts {
+            let idx = match part_run.part {
+                Part::One => 0,
+                Part::Two => 1,
+            };
+            parts[idx].push(part_run.elapsed);
+        }
+    }
+    Ok(BenchResult {
+        year,
+        day,
+        iterations: config.iterations,
+        parse: Stats::from_samples(&parse),
+        part_1: Stats::from_samples(&parts[0]),
+        part_2: Stats::from_samples(&parts[1]),
+    })
+}
+
+/// Formats the results as JSON so that runs before and after a change can be diffed or loaded
+/// into another tool.
+pub fn to_json(results: &[BenchResult]) -> String {
+    let entries = results
+        .iter()
+        .map(|r| {
+            format!(
+                "    {{\"year\": {}, \"day\": {}, \"iterations\": {}, \"parse\": {}, \"part_1\": {}, \"part_2\": {}}}",
+                r.year,
+                r.day,
+                r.iterations,
+                r.parse.to_json(),
+                r.part_1.to_json(),
+                r.part_2.to_json(),
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("{{\n  \"results\": [\n{}\n  ]\n}}", entries.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::SolverRegistry;
+
+    fn micros(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&v| Duration::from_micros(v)).collect()
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::from_samples(&micros(&[4, 2, 6, 8]));
+        assert_eq!(stats.min, Duration::from_micros(2));
+        assert_eq!(stats.median, Duration::from_micros(5));
+        assert_eq!(stats.mean, Duration::from_micros(5));
+        // sqrt(((1 + 9 + 1 + 9) / 4) µs²) = sqrt(5) µs
+        assert_eq!(stats.stddev, Duration::from_nanos(2236));
+
+        let stats = Stats::from_samples(&micros(&[7]));
+        assert_eq!(stats.median, Duration::from_micros(7));
+        assert_eq!(stats.stddev, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bench_day() {
+        let registry = SolverRegistry::with_all_years();
+        let solver = registry.get(2023, 2).unwrap();
+        let input = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
+        let config = BenchConfig { iterations: 3, warmup: 1 };
+        let result = bench_day(solver, 2023, 2, input, config).unwrap();
+        assert_eq!(result.iterations, 3);
+        assert!(result.parse.min <= result.parse.median);
+
+        let err = bench_day(solver, 2023, 2, "Game 1: 3 bleu", config).unwrap_err();
+        assert_eq!(err.line, Some(1));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let stats = Stats::from_samples(&micros(&[1]));
+        let result = BenchResult { year: 2023, day: 5, iterations: 1, parse: stats, part_1: stats, part_2: stats };
+        let stats_json = "{\"min_ns\": 1000, \"median_ns\": 1000, \"mean_ns\": 1000, \"stddev_ns\": 0}";
+        assert_eq!(
+            to_json(&[result]),
+            format!(
+                "{{\n  \"results\": [\n    {{\"year\": 2023, \"day\": 5, \"iterations\": 1, \"parse\": {stats_json}, \"part_1\": {stats_json}, \"part_2\": {stats_json}}}\n  ]\n}}"
+            )
+        );
+    }
+}
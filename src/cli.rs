@@ -0,0 +1,308 @@
+use std::fmt;
+use std::path::PathBuf;
+use crate::bench::BenchConfig;
+use crate::solver::Part;
+use crate::utils::lines::InputSource;
+
+pub const USAGE: &str = "\
+Usage: advent-of-rust <command> [options]
+
+Commands:
+  run <year> <day> [--part 1|2] [--input PATH|-] [--example] [--explain]
+                                                 Solve a single day, --explain also prints what
+                                                 the solver reported along the way
+  run --all [--part 1|2] [--example] [--explain] Solve every registered day
+  list                                           Show implemented and missing days
+  verify [--answers PATH] [--record]             Compare every day against the recorded answers,
+                                                 --record saves answers for parts without one
+  bench [<year> <day>] [--iterations N] [--warmup N] [--json]
+                                                 Time parsing and both parts of a day, or of
+                                                 every day, and report min/median/mean/stddev
+  help                                           Show this message
+
+Input:
+  By default a day reads inputs/<year>-day<day>.txt. --input reads another file, or stdin
+  when given `-`. --example reads the example from the puzzle text in inputs/examples.
+
+`advent-of-rust <year> <day>` is short for `advent-of-rust run <year> <day>`.";
+
+/// Answers are recorded here unless --answers says otherwise.
+pub const DEFAULT_ANSWERS_PATH: &str = "answers.txt";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Day { year: usize, day: u8 },
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run {
+        target: Target,
+        part: Option<Part>,
+        input: InputSource,
+        explain: bool,
+    },
+    List,
+    Verify {
+        answers: PathBuf,
+        record: bool,
+    },
+    Bench {
+        target: Target,
+        config: BenchConfig,
+        json: bool,
+    },
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliError(pub String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Parses the arguments that follow the program name.
+pub fn parse_args(args: &[String]) -> Result<Command, CliError> {
+    let Some(command) = args.first() else {
+        return Ok(Command::Help);
+    };
+    // Keep supporting the original `<year> <day>` invocation.
+    if command.parse::<usize>().is_ok() {
+        return parse_run(args);
+    }
+    let rest = &args[1..];
+    match command.as_str() {
+        "run" => parse_run(rest),
+        "list" => {
+            expect_no_more(rest)?;
+            Ok(Command::List)
+        }
+        "verify" => parse_verify(rest),
+        "bench" => parse_bench(rest),
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        other => Err(CliError(format!("unknown command {other:?}"))),
+    }
+}
+
+fn parse_run(args: &[String]) -> Result<Command, CliError> {
+    let mut all = false;
+    let mut part = None;
+    let mut input = InputSource::Personal;
+    let mut explain = false;
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--explain" => explain = true,
+            "--part" => part = Some(parse_part(flag_value(&mut args, "--part")?)?),
+            "--input" => input = set_input(input, parse_input(flag_value(&mut args, "--input")?))?,
+            "--example" => input = set_input(input, InputSource::Example)?,
+            flag if flag.starts_with('-') => return Err(CliError(format!("unknown option {flag:?}"))),
+            value => positional.push(value),
+        }
+    }
+    let target = parse_target(&positional, all)?;
+    if target == Target::All && !matches!(input, InputSource::Personal | InputSource::Example) {
+        return Err(CliError("--input can't be combined with --all".to_string()));
+    }
+    Ok(Command::Run { target, part, input, explain })
+}
+
+fn parse_input(value: &str) -> InputSource {
+    match value {
+        "-" => InputSource::Stdin,
+        path => InputSource::File(PathBuf::from(path)),
+    }
+}
+
+fn set_input(current: InputSource, new: InputSource) -> Result<InputSource, CliError> {
+    if current != InputSource::Personal {
+        return Err(CliError("only one of --input and --example can be given".to_string()));
+    }
+    Ok(new)
+}
+
+fn parse_verify(args: &[String]) -> Result<Command, CliError> {
+    let mut answers = PathBuf::from(DEFAULT_ANSWERS_PATH);
+    let mut record = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--answers" => answers = PathBuf::from(flag_value(&mut args, "--answers")?),
+            "--record" => record = true,
+            other => return Err(CliError(format!("unexpected argument {other:?}"))),
+        }
+    }
+    Ok(Command::Verify { answers, record })
+}
+
+fn parse_bench(args: &[String]) -> Result<Command, CliError> {
+    let mut config = BenchConfig::default();
+    let mut json = false;
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" => config.iterations = parse_count(flag_value(&mut args, "--iterations")?, "--iterations")?,
+            "--warmup" => config.warmup = parse_count(flag_value(&mut args, "--warmup")?, "--warmup")?,
+            "--json" => json = true,
+            flag if flag.starts_with('-') => return Err(CliError(format!("unknown option {flag:?}"))),
+            value => positional.push(value),
+        }
+    }
+    if config.iterations == 0 {
+        return Err(CliError("--iterations must be at least 1".to_string()));
+    }
+    // Without a day every registered solver is benchmarked.
+    let target = parse_target(&positional, positional.is_empty())?;
+    Ok(Command::Bench { target, config, json })
+}
+
+fn parse_count(value: &str, flag: &str) -> Result<usize, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError(format!("{flag} must be a number, found {value:?}")))
+}
+
+fn parse_target(positional: &[&str], all: bool) -> Result<Target, CliError> {
+    match (positional, all) {
+        ([], true) => Ok(Target::All),
+        ([year, day], false) => {
+            let year = year
+                .parse()
+                .map_err(|_| CliError(format!("year must be a number, found {year:?}")))?;
+            let day = day
+                .parse()
+                .map_err(|_| CliError(format!("day must be a number, found {day:?}")))?;
+            Ok(Target::Day { year, day })
+        }
+        (_, true) => Err(CliError("--all doesn't take a year or a day".to_string())),
+        _ => Err(CliError("expected <year> <day>".to_string())),
+    }
+}
+
+fn parse_part(value: &str) -> Result<Part, CliError> {
+    match value {
+        "1" => Ok(Part::One),
+        "2" => Ok(Part::Two),
+        other => Err(CliError(format!("part must be 1 or 2, found {other:?}"))),
+    }
+}
+
+fn flag_value<'a>(args: &mut impl Iterator<Item=&'a String>, flag: &str) -> Result<&'a str, CliError> {
+    args.next()
+        .map(|value| value.as_str())
+        .ok_or_else(|| CliError(format!("{flag} expects a value")))
+}
+
+fn expect_no_more(args: &[String]) -> Result<(), CliError> {
+    match args.first() {
+        Some(arg) => Err(CliError(format!("unexpected argument {arg:?}"))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, CliError> {
+        let args = args.split_whitespace().map(|s| s.to_string()).collect::<Vec<_>>();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn test_run() {
+        assert_eq!(
+            parse("run 2023 5 --part 2 --input other.txt"),
+            Ok(Command::Run {
+                target: Target::Day { year: 2023, day: 5 },
+                part: Some(Part::Two),
+                input: InputSource::File(PathBuf::from("other.txt")),
+                explain: false,
+            })
+        );
+        assert_eq!(
+            parse("run --all"),
+            Ok(Command::Run { target: Target::All, part: None, input: InputSource::Personal, explain: false })
+        );
+        // The original invocation still works.
+        assert_eq!(
+            parse("2023 8"),
+            Ok(Command::Run {
+                target: Target::Day { year: 2023, day: 8 },
+                part: None,
+                input: InputSource::Personal,
+                explain: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_input_sources() {
+        assert_eq!(
+            parse("run 2023 5 --input -"),
+            Ok(Command::Run {
+                target: Target::Day { year: 2023, day: 5 },
+                part: None,
+                input: InputSource::Stdin,
+                explain: false,
+            })
+        );
+        assert_eq!(
+            parse("run --all --example --part 1 --explain"),
+            Ok(Command::Run { target: Target::All, part: Some(Part::One), input: InputSource::Example, explain: true })
+        );
+        assert_eq!(
+            parse("run 2023 5 --example --input -"),
+            Err(CliError("only one of --input and --example can be given".to_string()))
+        );
+        assert_eq!(parse("run --all --input -"), Err(CliError("--input can't be combined with --all".to_string())));
+    }
+
+    #[test]
+    fn test_other_commands() {
+        assert_eq!(parse(""), Ok(Command::Help));
+        assert_eq!(parse("--help"), Ok(Command::Help));
+        assert_eq!(parse("list"), Ok(Command::List));
+        assert_eq!(
+            parse("verify"),
+            Ok(Command::Verify { answers: PathBuf::from(DEFAULT_ANSWERS_PATH), record: false })
+        );
+        assert_eq!(
+            parse("verify --record --answers other.txt"),
+            Ok(Command::Verify { answers: PathBuf::from("other.txt"), record: true })
+        );
+        assert_eq!(
+            parse("bench"),
+            Ok(Command::Bench { target: Target::All, config: BenchConfig::default(), json: false })
+        );
+        assert_eq!(
+            parse("bench 2023 7 --iterations 50 --warmup 0 --json"),
+            Ok(Command::Bench {
+                target: Target::Day { year: 2023, day: 7 },
+                config: BenchConfig { iterations: 50, warmup: 0 },
+                json: true,
+            })
+        );
+        assert_eq!(parse("bench --iterations 0"), Err(CliError("--iterations must be at least 1".to_string())));
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert_eq!(parse("run 2023"), Err(CliError("expected <year> <day>".to_string())));
+        assert_eq!(parse("run 2023 five"), Err(CliError("day must be a number, found \"five\"".to_string())));
+        assert_eq!(parse("run 2023 5 --part 3"), Err(CliError("part must be 1 or 2, found \"3\"".to_string())));
+        assert_eq!(parse("run 2023 5 --input"), Err(CliError("--input expects a value".to_string())));
+        assert_eq!(parse("run --all 2023 5"), Err(CliError("--all doesn't take a year or a day".to_string())));
+        assert_eq!(parse("list 2023"), Err(CliError("unexpected argument \"2023\"".to_string())));
+        assert_eq!(parse("solve"), Err(CliError("unknown command \"solve\"".to_string())));
+    }
+}
@@ -1,43 +1,282 @@
 extern crate core;
 
+use std::collections::BTreeMap;
 use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use crate::answers::{AnswerLedger, Status};
+use crate::bench::BenchConfig;
+use crate::cli::{Command, Target};
+use crate::registry::SolverRegistry;
+use crate::solver::{Answer, Part, Run};
+use crate::utils::lines::InputSource;
 
+mod answers;
+mod bench;
+mod cli;
+mod registry;
 mod utils;
 mod y2023;
 mod solver;
+#[cfg(test)]
+mod test_support;
+
+/// The process exits with this code when a solver fails or an answer doesn't match.
+const EXIT_FAILURE: i32 = 1;
+/// The process exits with this code when the arguments can't be understood.
+const EXIT_USAGE: i32 = 2;
 
 fn main() {
-    // Read from stdin
-    let args: Vec<String> = env::args().collect();
-
-    // Parse first argument as a uint8
-    let year: usize = args[1].parse().unwrap();
-    let day: u8 = args[2].parse().unwrap();
-
-    let solver = get_solver(year, day).unwrap();
-    // read file contents as an array of lines without using include_str
-//    let input = format!("./inputs/unit_test/day{:02}.txt", day);
-    let input = format!("./inputs/{:04}-day{:02}.txt", year, day);
-    // rust, read a file as a vector of strings
-    let lines = utils::lines::lines_from_file(input);
-    println!("Part 1: {}", solver.solve_part_1(lines.clone()));
-    println!("Part 2: {}", solver.solve_part_2(lines));
-}
-
-fn get_solver(year: usize, day: u8) -> Option<Box<dyn solver::Solver>> {
-    match year {
-        2023 => {
-            match day {
-                1 => Some(Box::new(y2023::day01::Day1Solver {})),
-                2 => Some(Box::new(y2023::day02::Day2Solver {})),
-                3 => Some(Box::new(y2023::day03::Day3Solver {})),
-                4 => Some(Box::new(y2023::day04::Day4Solver {})),
-                5 => Some(Box::new(y2023::day05::Day5Solver {})),
-                6 => Some(Box::new(y2023::day06::Day6Solver {})),
-                7 => Some(Box::new(y2023::day07::Day7Solver {})),
-                _ => None,
-            }
-        }
-        _ => None,
-    }
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{}", cli::USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    let registry = SolverRegistry::with_all_years();
+    let succeeded = match command {
+        Command::Run { target, part, input, explain } => {
+            let parts = part.map_or(Part::ALL.to_vec(), |part| vec![part]);
+            run(&registry, &target, &parts, &input, explain)
+        }
+        Command::List => {
+            list(&registry);
+            true
+        }
+        Command::Verify { answers, record } => verify(&registry, &answers, record),
+        Command::Bench { target, config, json } => bench(&registry, &target, config, json),
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            true
+        }
+    };
+    if !succeeded {
+        process::exit(EXIT_FAILURE);
+    }
+}
+
+/// Returns the days a command applies to, or None if the requested day has no solver.
+fn target_days(registry: &SolverRegistry, target: &Target) -> Option<Vec<(usize, u8)>> {
+    match *target {
+        Target::All => Some(registry.entries().collect()),
+        Target::Day { year, day } => match registry.get(year, day) {
+            Ok(_) => Some(vec![(year, day)]),
+            Err(err) => {
+                eprintln!("{err}");
+                eprintln!("implemented days for {year}: {:?}", registry.days(year));
+                None
+            }
+        },
+    }
+}
+
+/// Reads the input of a day and runs the requested parts on it. Parts that read the same input
+/// share a single Run; the example of a part can differ from the other part's, in which case each
+/// part gets its own. Errors are printed and turned into None so that callers can keep going with
+/// the next day. When `explain` is set the runs also hold the events reported by the solver.
+fn solve_day(
+    registry: &SolverRegistry,
+    year: usize,
+    day: u8,
+    parts: &[Part],
+    source: &InputSource,
+    explain: bool,
+) -> Option<Vec<Run>> {
+    let solver = registry.get(year, day).ok()?;
+    let mut groups: Vec<(Option<PathBuf>, Vec<Part>)> = vec![];
+    for &part in parts {
+        let path = source.path(year, day, part);
+        match groups.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, group)) => group.push(part),
+            None => groups.push((path, vec![part])),
+        }
+    }
+    let mut runs = vec![];
+    for (_, group) in groups {
+        let contents = match source.read(year, day, group[0]) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("could not read {}: {err}", source.describe(year, day, group[0]));
+                return None;
+            }
+        };
+        let run = match explain {
+            true => solver.run_explained(&contents, &group),
+            false => solver.run(&contents, &group),
+        };
+        match run {
+            Ok(run) => runs.push(run),
+            Err(err) => {
+                eprintln!("{year} day {day}: parsing failed: {err}");
+                return None;
+            }
+        }
+    }
+    Some(runs)
+}
+
+fn run(registry: &SolverRegistry, target: &Target, parts: &[Part], source: &InputSource, explain: bool) -> bool {
+    let Some(days) = target_days(registry, target) else {
+        return false;
+    };
+    let mut succeeded = true;
+    for (year, day) in days {
+        if *target == Target::All {
+            println!("== {year} day {day:02} ==");
+        }
+        let Some(runs) = solve_day(registry, year, day, parts, source, explain) else {
+            succeeded = false;
+            continue;
+        };
+        for run in runs {
+            println!("Parsed in {:?}", run.parse_time);
+            for part_run in run.parts.iter() {
+                match &part_run.answer {
+                    Ok(Answer::Grid(rows)) => println!("Part {} ({:?}):\n{}", part_run.part, part_run.elapsed, rows.join("\n")),
+                    Ok(answer) => println!("Part {} ({:?}): {}", part_run.part, part_run.elapsed, answer),
+                    Err(err) => {
+                        eprintln!("Part {} failed: {}", part_run.part, err);
+                        succeeded = false;
+                    }
+                }
+                for event in part_run.events.iter() {
+                    println!("  {event}");
+                }
+            }
+        }
+    }
+    succeeded
+}
+
+fn list(registry: &SolverRegistry) {
+    let mut years = registry.entries().map(|(year, _)| year).collect::<Vec<_>>();
+    years.dedup();
+    for year in years {
+        let days = registry.days(year);
+        let missing = registry.missing_days(year);
+        println!("{year}: {} implemented, {} missing", days.len(), missing.len());
+        println!("  implemented: {}", join_days(&days));
+        if !missing.is_empty() {
+            println!("  missing:     {}", join_days(&missing));
+        }
+    }
+}
+
+fn join_days(days: &[u8]) -> String {
+    days.iter().map(|day| day.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn verify(registry: &SolverRegistry, answers_path: &Path, record: bool) -> bool {
+    let mut ledger = match AnswerLedger::load(answers_path) {
+        Ok(ledger) => ledger,
+        // Recording into a ledger that doesn't exist yet starts a new one.
+        Err(err) if record && err.kind() == io::ErrorKind::NotFound => AnswerLedger::default(),
+        Err(err) => {
+            eprintln!("could not load answers from {}: {err}", answers_path.display());
+            return false;
+        }
+    };
+    let mut succeeded = true;
+    let mut details = vec![];
+    let mut recorded = 0;
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    println!("{:<12} {:<8} part 2", "day", "part 1");
+    for (year, day) in registry.entries() {
+        let Some(runs) = solve_day(registry, year, day, &Part::ALL, &InputSource::Personal, false) else {
+            succeeded = false;
+            println!("{:<12} {:<8} ERROR", format!("{year} day {day:02}"), "ERROR");
+            continue;
+        };
+        let mut labels = vec![];
+        for part_run in runs.iter().flat_map(|run| run.parts.iter()) {
+            let status = ledger.check(year, day, part_run.part, &part_run.answer);
+            *counts.entry(status.label()).or_default() += 1;
+            labels.push(status.label());
+            if status.is_failure() {
+                succeeded = false;
+            }
+            if status != Status::Pass {
+                details.push(format!("{year} day {day:02} part {}: {status}", part_run.part));
+            }
+            if let (true, Status::Missing { actual }) = (record, &status) {
+                ledger.record(year, day, part_run.part, actual);
+                recorded += 1;
+            }
+        }
+        println!("{:<12} {:<8} {}", format!("{year} day {day:02}"), labels[0], labels[1]);
+    }
+
+    if !details.is_empty() {
+        println!();
+        for detail in details {
+            println!("{detail}");
+        }
+    }
+    let summary = counts.iter().map(|(label, count)| format!("{count} {label}")).collect::<Vec<_>>();
+    println!("\n{}", summary.join(", "));
+
+    if recorded > 0 {
+        if let Err(err) = ledger.save(answers_path) {
+            eprintln!("could not save answers to {}: {err}", answers_path.display());
+            return false;
+        }
+        println!("recorded {recorded} new answers in {}", answers_path.display());
+    }
+    succeeded
+}
+
+fn bench(registry: &SolverRegistry, target: &Target, config: BenchConfig, json: bool) -> bool {
+    let Some(days) = target_days(registry, target) else {
+        return false;
+    };
+    let mut succeeded = true;
+    let mut results = vec![];
+    for (year, day) in days {
+        let source = InputSource::Personal;
+        let input = match source.read(year, day, Part::One) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("could not read {}: {err}", source.describe(year, day, Part::One));
+                succeeded = false;
+                continue;
+            }
+        };
+        let solver = registry.get(year, day).expect("target days are registered");
+        match bench::bench_day(solver, year, day, &input, config) {
+            Ok(result) => results.push(result),
+            Err(err) => {
+                eprintln!("{year} day {day}: {err}");
+                succeeded = false;
+            }
+        }
+    }
+
+    if json {
+        println!("{}", bench::to_json(&results));
+        return succeeded;
+    }
+    println!("{} iterations after {} warmup runs", config.iterations, config.warmup);
+    println!(
+        "{:<12} {:<7} {:>12} {:>12} {:>12} {:>12}",
+        "day", "phase", "min", "median", "mean", "stddev"
+    );
+    for result in results {
+        let phases = [("parse", result.parse), ("part 1", result.part_1), ("part 2", result.part_2)];
+        for (phase, stats) in phases {
+            println!(
+                "{:<12} {:<7} {:>12} {:>12} {:>12} {:>12}",
+                format!("{} day {:02}", result.year, result.day),
+                phase,
+                format!("{:.2?}", stats.min),
+                format!("{:.2?}", stats.median),
+                format!("{:.2?}", stats.mean),
+                format!("{:.2?}", stats.stddev),
+            );
+        }
+    }
+    succeeded
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::solver::DynSolver;
+use crate::y2023;
+
+/// Advent of Code runs from the 1st to the 25th of December.
+pub const DAYS_PER_YEAR: u8 = 25;
+
+/// SolverRegistry holds every available solver keyed by (year, day). Each year module adds its
+/// own days through a `register` function, so adding a day never requires touching main.rs.
+pub struct SolverRegistry {
+    solvers: BTreeMap<(usize, u8), Box<dyn DynSolver>>,
+}
+
+impl SolverRegistry {
+    pub fn new() -> Self {
+        SolverRegistry {
+            solvers: BTreeMap::new(),
+        }
+    }
+
+    /// Returns a registry with the solvers of every year.
+    pub fn with_all_years() -> Self {
+        let mut registry = Self::new();
+        y2023::register(&mut registry);
+        registry
+    }
+
+    pub fn register(&mut self, year: usize, day: u8, solver: Box<dyn DynSolver>) {
+        if self.solvers.insert((year, day), solver).is_some() {
+            panic!("solver for {year} day {day} registered twice");
+        }
+    }
+
+    pub fn get(&self, year: usize, day: u8) -> Result<&dyn DynSolver, RegistryError> {
+        self.solvers
+            .get(&(year, day))
+            .map(|solver| solver.as_ref())
+            .ok_or(RegistryError::NoSolver { year, day })
+    }
+
+    /// Returns every registered (year, day) in ascending order.
+    pub fn entries(&self) -> impl Iterator<Item=(usize, u8)> + '_ {
+        self.solvers.keys().copied()
+    }
+
+    pub fn days(&self, year: usize) -> Vec<u8> {
+        self.entries()
+            .filter(|(y, _)| *y == year)
+            .map(|(_, day)| day)
+            .collect()
+    }
+
+    /// Returns the days of the given year that don't have a solver yet.
+    pub fn missing_days(&self, year: usize) -> Vec<u8> {
+        (1..=DAYS_PER_YEAR)
+            .filter(|day| !self.solvers.contains_key(&(year, *day)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RegistryError {
+    NoSolver { year: usize, day: u8 },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::NoSolver { year, day } => write!(f, "no solver for {year} day {day}"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_2023_days_registered() {
+        let registry = SolverRegistry::with_all_years();
+        assert_eq!(registry.days(2023), (1..=8).collect::<Vec<u8>>());
+        assert_eq!(registry.missing_days(2023), (9..=25).collect::<Vec<u8>>());
+        assert!(registry.get(2023, 8).is_ok());
+    }
+
+    #[test]
+    fn test_missing_solver() {
+        let registry = SolverRegistry::with_all_years();
+        let err = registry.get(2023, 26).err().unwrap();
+        assert_eq!(err, RegistryError::NoSolver { year: 2023, day: 26 });
+        assert_eq!(err.to_string(), "no solver for 2023 day 26");
+        assert!(registry.get(2015, 1).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "registered twice")]
+    fn test_duplicate_registration() {
+        let mut registry = SolverRegistry::new();
+        y2023::register(&mut registry);
+        y2023::register(&mut registry);
+    }
+}
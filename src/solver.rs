@@ -1,4 +1,351 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Solver solves both parts of a single day. The input is parsed once into `Input` and both parts
+/// share the parsed value, so parsing is paid for (and timed) only once.
 pub trait Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String;
-    fn solve_part_2(&self, lines: Vec<String>) -> String;
-}
\ No newline at end of file
+    type Input;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError>;
+    fn solve_part_1(&self, input: &Self::Input) -> Result<Answer, SolveError>;
+    fn solve_part_2(&self, input: &Self::Input) -> Result<Answer, SolveError>;
+
+    /// Solves a part while reporting what the solver finds along the way to `events`. Solvers
+    /// with something to report override this and have their `solve_part_*` pass `Discard`, the
+    /// others keep this default that solves the part without reporting anything.
+    fn solve_explained(&self, input: &Self::Input, part: Part, events: &mut dyn EventSink) -> Result<Answer, SolveError> {
+        let _ = events;
+        match part {
+            Part::One => self.solve_part_1(input),
+            Part::Two => self.solve_part_2(input),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Part {
+    One,
+    Two,
+}
+
+impl Part {
+    pub const ALL: [Part; 2] = [Part::One, Part::Two];
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Part::One => write!(f, "1"),
+            Part::Two => write!(f, "2"),
+        }
+    }
+}
+
+/// PartRun is the outcome of solving one part together with how long it took.
+#[derive(Debug)]
+pub struct PartRun {
+    pub part: Part,
+    pub answer: Result<Answer, SolveError>,
+    pub elapsed: Duration,
+    /// What the solver reported while solving the part. Only collected by `run_explained`.
+    pub events: Vec<Event>,
+}
+
+/// Run is the outcome of parsing an input once and solving the requested parts with it.
+#[derive(Debug)]
+pub struct Run {
+    pub parse_time: Duration,
+    pub parts: Vec<PartRun>,
+}
+
+/// DynSolver hides the `Input` type of a Solver so that solvers of every day can be stored side
+/// by side in the registry. It is implemented for every Solver.
+pub trait DynSolver {
+    fn run(&self, input: &str, parts: &[Part]) -> Result<Run, SolveError>;
+
+    /// Like `run`, but collects the events the solver reports while solving each part.
+    fn run_explained(&self, input: &str, parts: &[Part]) -> Result<Run, SolveError>;
+}
+
+impl<S: Solver> DynSolver for S {
+    fn run(&self, input: &str, parts: &[Part]) -> Result<Run, SolveError> {
+        run_parts(self, input, parts, false)
+    }
+
+    fn run_explained(&self, input: &str, parts: &[Part]) -> Result<Run, SolveError> {
+        run_parts(self, input, parts, true)
+    }
+}
+
+fn run_parts<S: Solver>(solver: &S, input: &str, parts: &[Part], explain: bool) -> Result<Run, SolveError> {
+    let start = Instant::now();
+    let parsed = solver.parse(input)?;
+    let parse_time = start.elapsed();
+    let parts = parts
+        .iter()
+        .map(|&part| {
+            let mut events = vec![];
+            let start = Instant::now();
+            let answer = match (explain, part) {
+                (true, _) => solver.solve_explained(&parsed, part, &mut events),
+                (false, Part::One) => solver.solve_part_1(&parsed),
+                (false, Part::Two) => solver.solve_part_2(&parsed),
+            };
+            PartRun {
+                part,
+                answer,
+                elapsed: start.elapsed(),
+                events,
+            }
+        })
+        .collect();
+    Ok(Run { parse_time, parts })
+}
+
+/// Event is a fact a solver reports while it works, like how it read a piece of the input, made
+/// of a name and a list of fields. It prints as `name key=value key=value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Event {
+    pub fn new(name: impl Into<String>) -> Self {
+        Event {
+            name: name.into(),
+            fields: vec![],
+        }
+    }
+
+    pub fn with(mut self, key: impl Into<String>, value: impl fmt::Display) -> Self {
+        self.fields.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// Returns the value of a field, for tests that check what a solver reported.
+    #[cfg(test)]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for (key, value) in self.fields.iter() {
+            // Quote values that would otherwise run into the next field.
+            if value.is_empty() || value.contains(char::is_whitespace) {
+                write!(f, " {key}={value:?}")?;
+            } else {
+                write!(f, " {key}={value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// EventSink receives the events a solver reports. Solvers should check `enabled` before they
+/// build an event, so that reporting costs nothing when nobody is listening.
+pub trait EventSink {
+    fn enabled(&self) -> bool;
+    fn record(&mut self, event: Event);
+}
+
+/// Discard drops every event.
+pub struct Discard;
+
+impl EventSink for Discard {
+    fn enabled(&self) -> bool {
+        false
+    }
+
+    fn record(&mut self, _event: Event) {}
+}
+
+/// A Vec keeps every event, to print them once the part is solved or to check them in tests.
+impl EventSink for Vec<Event> {
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn record(&mut self, event: Event) {
+        self.push(event);
+    }
+}
+
+/// Answer is what a part of a puzzle evaluates to. Most puzzles ask for a number, but some ask
+/// for a word or for a picture drawn on a grid of characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Integer(i128),
+    Text(String),
+    // None of the 2023 puzzles so far answer with a picture.
+    #[allow(dead_code)]
+    Grid(Vec<String>),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Integer(value) => write!(f, "{value}"),
+            Answer::Text(text) => write!(f, "{text}"),
+            Answer::Grid(rows) => write!(f, "{}", rows.join("\n")),
+        }
+    }
+}
+
+macro_rules! answer_from_integer {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Answer {
+                fn from(value: $t) -> Self {
+                    // Every type listed below fits in an i128, u64 included.
+                    Answer::Integer(value as i128)
+                }
+            }
+        )*
+    };
+}
+
+answer_from_integer!(u8, u32, u64, usize, i32, i64, isize);
+
+impl From<String> for Answer {
+    fn from(value: String) -> Self {
+        Answer::Text(value)
+    }
+}
+
+impl From<&str> for Answer {
+    fn from(value: &str) -> Self {
+        Answer::Text(value.to_string())
+    }
+}
+
+/// Lets tests compare an answer against the text the puzzle website expects.
+impl PartialEq<&str> for Answer {
+    fn eq(&self, other: &&str) -> bool {
+        match self {
+            Answer::Integer(value) => other.parse::<i128>() == Ok(*value),
+            Answer::Text(text) => text == other,
+            Answer::Grid(rows) => rows.join("\n") == *other,
+        }
+    }
+}
+
+/// SolveError explains why a solver could not produce an answer. Errors raised while reading the
+/// input carry the (1-based) line they happened on and what was being parsed at the time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolveError {
+    pub line: Option<usize>,
+    pub context: Option<String>,
+    pub message: String,
+}
+
+impl SolveError {
+    pub fn new(message: impl Into<String>) -> Self {
+        SolveError {
+            line: None,
+            context: None,
+            message: message.into(),
+        }
+    }
+
+    /// Attaches the line the error happened on. Takes the 0-based index used when enumerating
+    /// the input lines.
+    pub fn with_line(mut self, line_idx: usize) -> Self {
+        self.line = Some(line_idx + 1);
+        self
+    }
+
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        if let Some(context) = &self.context {
+            write!(f, "{context}: ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer_display() {
+        assert_eq!(Answer::from(42usize).to_string(), "42");
+        assert_eq!(Answer::from(-3i64).to_string(), "-3");
+        assert_eq!(Answer::from("ABC").to_string(), "ABC");
+        let grid = Answer::Grid(vec!["#.".to_string(), ".#".to_string()]);
+        assert_eq!(grid.to_string(), "#.\n.#");
+        assert_eq!(Answer::from(42u64), "42");
+        assert_eq!(Answer::from(u64::MAX), Answer::Integer(18446744073709551615));
+        assert_eq!(Answer::from(u64::MAX), "18446744073709551615");
+    }
+
+    struct WordCount {}
+
+    impl Solver for WordCount {
+        type Input = Vec<String>;
+
+        fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+            Ok(input.split_whitespace().map(|s| s.to_string()).collect())
+        }
+
+        fn solve_part_1(&self, input: &Self::Input) -> Result<Answer, SolveError> {
+            Ok(input.len().into())
+        }
+
+        fn solve_part_2(&self, input: &Self::Input) -> Result<Answer, SolveError> {
+            input.last().map(|s| Answer::from(s.as_str())).ok_or(SolveError::new("empty input"))
+        }
+    }
+
+    #[test]
+    fn test_dyn_solver_run() {
+        let solver: Box<dyn DynSolver> = Box::new(WordCount {});
+        let run = solver.run("a b c", &Part::ALL).unwrap();
+        assert_eq!(run.parts.len(), 2);
+        assert_eq!(run.parts[0].part, Part::One);
+        assert_eq!(run.parts[0].answer, Ok(Answer::Integer(3)));
+        assert_eq!(run.parts[1].answer, Ok(Answer::from("c")));
+
+        let run = solver.run("", &[Part::Two]).unwrap();
+        assert_eq!(run.parts.len(), 1);
+        assert_eq!(run.parts[0].answer, Err(SolveError::new("empty input")));
+
+        // WordCount doesn't report anything, but can still be run while explaining.
+        let run = solver.run_explained("a b c", &[Part::One]).unwrap();
+        assert_eq!(run.parts[0].answer, Ok(Answer::Integer(3)));
+        assert!(run.parts[0].events.is_empty());
+    }
+
+    #[test]
+    fn test_event_display() {
+        let event = Event::new("hand").with("cards", "KTJJT").with("rank", 5).with("level", "four of a kind");
+        assert_eq!(event.to_string(), "hand cards=KTJJT rank=5 level=\"four of a kind\"");
+        assert_eq!(event.get("rank"), Some("5"));
+        assert_eq!(Event::new("empty").with("value", "").to_string(), "empty value=\"\"");
+    }
+
+    #[test]
+    fn test_solve_error_display() {
+        let err = SolveError::new("expected a number at column 6")
+            .with_line(2)
+            .with_context("parsing card");
+        assert_eq!(err.line, Some(3));
+        assert_eq!(err.to_string(), "line 3: parsing card: expected a number at column 6");
+        assert_eq!(SolveError::new("no solution").to_string(), "no solution");
+    }
+}
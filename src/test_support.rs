@@ -0,0 +1,90 @@
+//! Helpers for loading puzzle inputs in tests. Paths are resolved from the crate root rather than
+//! the working directory, so tests behave the same no matter where cargo is run from.
+
+use std::path::{Path, PathBuf};
+use crate::solver::Part;
+use crate::utils::lines::{default_input_path, example_path_in, input_from_file};
+
+fn crate_root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+pub fn personal_input_path(year: usize, day: u8) -> PathBuf {
+    crate_root().join(default_input_path(year, day))
+}
+
+/// Returns the personal input of a day. Personal inputs aren't meant to be shared, so a checkout
+/// may not have them; in that case a message is printed and None is returned so the test can be
+/// skipped. See `require_input!`.
+pub fn personal_input(year: usize, day: u8) -> Option<String> {
+    let path = personal_input_path(year, day);
+    match input_from_file(&path) {
+        Ok(input) => Some(input),
+        Err(_) => {
+            eprintln!("skipping: no personal input for {year} day {day} at {}", path.display());
+            None
+        }
+    }
+}
+
+/// Returns the example from the puzzle text for the given part. Examples are checked in, so a
+/// missing one is a bug and panics.
+pub fn example(year: usize, day: u8, part: Part) -> String {
+    let path = example_path_in(crate_root(), year, day, part);
+    input_from_file(&path).unwrap_or_else(|err| panic!("could not read example {}: {err}", path.display()))
+}
+
+/// Evaluates to the personal input of a day, or returns from the calling test when the input
+/// isn't present.
+macro_rules! require_input {
+    ($year:expr, $day:expr) => {
+        match $crate::test_support::personal_input($year, $day) {
+            Some(input) => input,
+            None => return,
+        }
+    };
+}
+
+pub(crate) use require_input;
+
+/// Rng is a small xorshift generator for tests that check a property over many generated cases.
+/// It's seeded explicitly so that a failing case can be reproduced.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero.
+        Rng(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_personal_input_is_skipped() {
+        assert_eq!(personal_input(1999, 1), None);
+        assert!(personal_input_path(2023, 5).ends_with("inputs/2023-day05.txt"));
+    }
+
+    #[test]
+    fn test_example_per_part() {
+        assert!(example(2023, 1, Part::One).starts_with("1abc2"));
+        assert!(example(2023, 1, Part::Two).starts_with("two1nine"));
+        // Day 5 shares one example between both parts.
+        assert_eq!(example(2023, 5, Part::One), example(2023, 5, Part::Two));
+    }
+}
@@ -0,0 +1,417 @@
+// Grid helpers shared by the days that read maps of characters; some of them have no caller yet.
+#![allow(dead_code)]
+
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use crate::solver::SolveError;
+
+/// Pos is the position of a cell, counted from the top left corner of the grid.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Pos {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Pos {
+    pub fn new(row: usize, col: usize) -> Self {
+        Pos { row, col }
+    }
+
+    /// Returns the position `step` away, or None if it would be left of or above the grid.
+    pub fn step(self, step: Step) -> Option<Pos> {
+        Some(Pos {
+            row: self.row.checked_add_signed(step.0)?,
+            col: self.col.checked_add_signed(step.1)?,
+        })
+    }
+}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.row, self.col)
+    }
+}
+
+/// Step is a move of (rows, columns). Rows grow downwards and columns to the right.
+pub type Step = (isize, isize);
+
+pub const UP: Step = (-1, 0);
+pub const DOWN: Step = (1, 0);
+pub const LEFT: Step = (0, -1);
+pub const RIGHT: Step = (0, 1);
+
+/// The four cells that share a side with a cell, clockwise from the one above.
+pub const ORTHOGONAL: [Step; 4] = [UP, RIGHT, DOWN, LEFT];
+/// The eight cells that share a side or a corner with a cell, clockwise from the one above.
+pub const AROUND: [Step; 8] = [(-1, 0), (-1, 1), (0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1)];
+
+/// Grid is a rectangle of cells stored row by row, like the maps drawn by many puzzle inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T> Grid<T> {
+    /// Reads a grid with one row per line, turning each character into a cell. A character is a
+    /// code point together with the combining marks that follow it, so an `e` followed by a
+    /// combining accent takes a single cell like `é` does. Every row must be as wide as the first
+    /// one.
+    pub fn parse<'a>(
+        input: &'a str,
+        cell: impl FnMut(Pos, &'a str) -> Result<T, SolveError>,
+    ) -> Result<Self, SolveError> {
+        let rows = Self::parse_rows(input, cell)?;
+        let width = rows.first().map_or(0, Vec::len);
+        if let Some((row, cells)) = rows.iter().enumerate().find(|(_, cells)| cells.len() != width) {
+            return Err(SolveError::new(format!("row is {} cells wide but the first row is {width}", cells.len()))
+                .with_line(row)
+                .with_context("parsing grid"));
+        }
+        Ok(Self::from_rows(rows, width))
+    }
+
+    /// Reads a grid like `parse`, but lets rows be of any width. Rows shorter than the widest one
+    /// are padded with `fill`.
+    pub fn parse_padded<'a>(
+        input: &'a str,
+        fill: T,
+        cell: impl FnMut(Pos, &'a str) -> Result<T, SolveError>,
+    ) -> Result<Self, SolveError>
+    where
+        T: Clone,
+    {
+        let mut rows = Self::parse_rows(input, cell)?;
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        for cells in rows.iter_mut() {
+            cells.resize(width, fill.clone());
+        }
+        Ok(Self::from_rows(rows, width))
+    }
+
+    fn parse_rows<'a>(
+        input: &'a str,
+        mut cell: impl FnMut(Pos, &'a str) -> Result<T, SolveError>,
+    ) -> Result<Vec<Vec<T>>, SolveError> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(row, line)| {
+                characters(line)
+                    .enumerate()
+                    .map(|(col, c)| cell(Pos::new(row, col), c).map_err(|err| err.with_line(row)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn from_rows(rows: Vec<Vec<T>>, width: usize) -> Self {
+        Grid {
+            height: rows.len(),
+            cells: rows.into_iter().flatten().collect(),
+            width,
+        }
+    }
+
+    /// Builds a grid of the given size with every cell set to `value`.
+    pub fn filled(width: usize, height: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Grid {
+            cells: vec![value; width * height],
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.row < self.height && pos.col < self.width
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        self.contains(pos).then(|| &self.cells[pos.row * self.width + pos.col])
+    }
+
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        match self.contains(pos) {
+            true => Some(&mut self.cells[pos.row * self.width + pos.col]),
+            false => None,
+        }
+    }
+
+    /// Returns the position `step` away, or None if it is outside the grid.
+    pub fn step(&self, pos: Pos, step: Step) -> Option<Pos> {
+        pos.step(step).filter(|&next| self.contains(next))
+    }
+
+    /// Returns the cells that share a side with `pos` and are inside the grid.
+    pub fn neighbours4(&self, pos: Pos) -> impl Iterator<Item=Pos> + '_ {
+        ORTHOGONAL.into_iter().filter_map(move |step| self.step(pos, step))
+    }
+
+    /// Returns the cells that share a side or a corner with `pos` and are inside the grid.
+    pub fn neighbours8(&self, pos: Pos) -> impl Iterator<Item=Pos> + '_ {
+        AROUND.into_iter().filter_map(move |step| self.step(pos, step))
+    }
+
+    /// Returns every position from `start` onwards, moving by `step` until leaving the grid. This
+    /// walks rows, columns and diagonals, e.g. `walk(Pos::new(0, 0), (1, 1))` is the main diagonal.
+    pub fn walk(&self, start: Pos, step: Step) -> impl Iterator<Item=Pos> + '_ {
+        std::iter::successors(Some(start).filter(|&pos| self.contains(pos)), move |&pos| self.step(pos, step))
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.cells[row * self.width..(row + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item=&[T]> + '_ {
+        (0..self.height).map(|row| self.row(row))
+    }
+
+    pub fn column(&self, col: usize) -> impl Iterator<Item=&T> + '_ {
+        self.walk(Pos::new(0, col), DOWN).map(|pos| &self[pos])
+    }
+
+    /// Returns every position from left to right, top to bottom.
+    pub fn positions(&self) -> impl Iterator<Item=Pos> {
+        let width = self.width;
+        (0..self.height).flat_map(move |row| (0..width).map(move |col| Pos::new(row, col)))
+    }
+
+    /// Returns every cell with its position from left to right, top to bottom.
+    pub fn iter(&self) -> impl Iterator<Item=(Pos, &T)> + '_ {
+        self.positions().zip(self.cells.iter())
+    }
+
+    /// Returns the horizontal runs of consecutive cells that match, from left to right, top to
+    /// bottom. Runs stop at the end of a row.
+    pub fn runs(&self, matches: impl Fn(&T) -> bool) -> Vec<Run> {
+        let mut runs = vec![];
+        for (row, cells) in self.rows().enumerate() {
+            let mut start = None;
+            for (col, cell) in cells.iter().enumerate() {
+                match (start, matches(cell)) {
+                    (None, true) => start = Some(col),
+                    (Some(from), false) => {
+                        runs.push(Run { start: Pos::new(row, from), len: col - from });
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(from) = start {
+                runs.push(Run { start: Pos::new(row, from), len: self.width - from });
+            }
+        }
+        runs
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            cells: self.cells.iter().map(f).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+impl Grid<char> {
+    /// Reads a grid that keeps every character as it is. Characters written with combining marks
+    /// don't fit in a char, so they are rejected.
+    pub fn chars(input: &str) -> Result<Self, SolveError> {
+        Grid::parse(input, |pos, cell| {
+            let mut chars = cell.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(SolveError::new(format!("{cell:?} in column {} is more than one char", pos.col + 1))
+                    .with_context("parsing grid")),
+            }
+        })
+    }
+}
+
+/// Splits a line into its characters, each a code point followed by its combining marks.
+fn characters(line: &str) -> impl Iterator<Item=&str> {
+    let mut starts = line
+        .char_indices()
+        .filter(|&(idx, c)| idx == 0 || !is_combining_mark(c))
+        .map(|(idx, _)| idx)
+        .peekable();
+    std::iter::from_fn(move || {
+        let start = starts.next()?;
+        Some(&line[start..starts.peek().copied().unwrap_or(line.len())])
+    })
+}
+
+/// Checks for the marks that are drawn over the character before them: the combining blocks of
+/// Unicode, and the variation selectors that pick how a character is drawn.
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        self.get(pos)
+            .unwrap_or_else(|| panic!("{pos} is outside of a {}x{} grid", self.width, self.height))
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        let (width, height) = (self.width, self.height);
+        self.get_mut(pos)
+            .unwrap_or_else(|| panic!("{pos} is outside of a {width}x{height} grid"))
+    }
+}
+
+/// Prints the grid back as text, one line per row.
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, row) in self.rows().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{cell}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run is a horizontal stretch of `len` cells starting at `start`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Run {
+    pub start: Pos,
+    pub len: usize,
+}
+
+impl Run {
+    /// Returns the position of the last cell of the run.
+    pub fn end(&self) -> Pos {
+        Pos::new(self.start.row, self.start.col + self.len - 1)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item=Pos> {
+        let row = self.start.row;
+        (self.start.col..self.start.col + self.len).map(move |col| Pos::new(row, col))
+    }
+
+    /// Returns the cells of the run.
+    pub fn cells<'a, T>(&self, grid: &'a Grid<T>) -> &'a [T] {
+        &grid.row(self.start.row)[self.start.col..self.start.col + self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMATIC: &str = "467..114..\n...*......\n..35..633.";
+
+    #[test]
+    fn test_parse_and_display() {
+        let grid = Grid::chars(SCHEMATIC).unwrap();
+        assert_eq!((grid.width(), grid.height()), (10, 3));
+        assert_eq!(grid[Pos::new(1, 3)], '*');
+        assert_eq!(grid.get(Pos::new(3, 0)), None);
+        assert_eq!(grid.get(Pos::new(0, 10)), None);
+        assert_eq!(grid.to_string(), SCHEMATIC);
+
+        let digits = Grid::parse("12\n34", |_, c| Ok(c.parse::<u32>().unwrap())).unwrap();
+        assert_eq!(digits.map(|d| d * 2).to_string(), "24\n68");
+        assert_eq!(Grid::chars("").unwrap().width(), 0);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = Grid::chars("abc\nab").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: parsing grid: row is 2 cells wide but the first row is 3");
+        let err = Grid::parse("..\n.x", |_, c| match c {
+            "." => Ok(false),
+            other => Err(SolveError::new(format!("unexpected {other:?}"))),
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unexpected \"x\"");
+        // Padding fills the short rows up to the widest one, wherever it is.
+        let grid = Grid::parse_padded("ab\nabcd\n\na", '.', |_, c| Ok(c.chars().next().unwrap())).unwrap();
+        assert_eq!((grid.width(), grid.height()), (4, 4));
+        assert_eq!(grid.to_string(), "ab..\nabcd\n....\na...");
+        // Columns count characters, so multi-byte characters are a single cell.
+        let grid = Grid::chars("é.\n.€").unwrap();
+        assert_eq!(grid[Pos::new(1, 1)], '€');
+        // A combining accent stays in the cell of the character it is drawn over.
+        let grid = Grid::parse("e\u{301}.\n\u{301}\u{302}a\u{20DD}", |_, c| Ok(c.to_string())).unwrap();
+        assert_eq!(grid.row(0), ["e\u{301}", "."]);
+        assert_eq!(grid.row(1), ["\u{301}\u{302}", "a\u{20DD}"]);
+        let err = Grid::chars("..\n.e\u{301}").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: parsing grid: \"e\\u{301}\" in column 2 is more than one char");
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let grid = Grid::chars(SCHEMATIC).unwrap();
+        assert_eq!(grid.neighbours4(Pos::new(0, 0)).collect::<Vec<_>>(), vec![Pos::new(0, 1), Pos::new(1, 0)]);
+        assert_eq!(grid.neighbours8(Pos::new(0, 0)).count(), 3);
+        assert_eq!(grid.neighbours8(Pos::new(1, 3)).count(), 8);
+        assert_eq!(grid.neighbours8(Pos::new(2, 9)).count(), 3);
+        let around_gear = grid.neighbours8(Pos::new(1, 3)).map(|pos| grid[pos]).collect::<String>();
+        assert_eq!(around_gear, "....53.7");
+    }
+
+    #[test]
+    fn test_walks() {
+        let grid = Grid::chars("abc\ndef\nghi").unwrap();
+        assert_eq!(grid.row(1), ['d', 'e', 'f']);
+        assert_eq!(grid.column(2).collect::<String>(), "cfi");
+        let diagonal = |start, step| grid.walk(start, step).map(|pos| grid[pos]).collect::<String>();
+        assert_eq!(diagonal(Pos::new(0, 0), (1, 1)), "aei");
+        assert_eq!(diagonal(Pos::new(0, 2), (1, -1)), "ceg");
+        assert_eq!(diagonal(Pos::new(2, 2), LEFT), "ihg");
+        assert_eq!(diagonal(Pos::new(3, 0), RIGHT), "");
+        assert_eq!(grid.iter().nth(4), Some((Pos::new(1, 1), &'e')));
+    }
+
+    #[test]
+    fn test_runs() {
+        let grid = Grid::chars(SCHEMATIC).unwrap();
+        let runs = grid.runs(|c| c.is_ascii_digit());
+        let numbers = runs.iter().map(|run| run.cells(&grid).iter().collect::<String>()).collect::<Vec<_>>();
+        assert_eq!(numbers, vec!["467", "114", "35", "633"]);
+        assert_eq!(runs[1], Run { start: Pos::new(0, 5), len: 3 });
+        assert_eq!(runs[1].end(), Pos::new(0, 7));
+        assert_eq!(runs[3].positions().last(), Some(Pos::new(2, 8)));
+
+        // A run can reach the end of its row without joining the next one.
+        let grid = Grid::chars(".11\n11.").unwrap();
+        assert_eq!(grid.runs(|&c| c == '1').len(), 2);
+    }
+
+    #[test]
+    fn test_index_mut() {
+        let mut grid = Grid::filled(3, 2, '.');
+        grid[Pos::new(1, 2)] = '#';
+        assert_eq!(grid.to_string(), "...\n..#");
+        assert_eq!(grid.get_mut(Pos::new(2, 0)), None);
+    }
+}
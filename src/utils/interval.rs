@@ -0,0 +1,645 @@
+// A general-purpose interval library, so not every operation has a caller yet.
+#![allow(dead_code)]
+
+use std::fmt;
+
+/// Discrete is implemented by the integer types that intervals can be built from. Offsets between
+/// values are expressed as i128 so that any two values of any of these types have one.
+pub trait Discrete: Copy + Ord + fmt::Debug + fmt::Display {
+    const MIN: Self;
+    const MAX: Self;
+
+    /// Returns `self + delta`, or None if that doesn't fit in the type.
+    fn checked_shift(self, delta: i128) -> Option<Self>;
+
+    /// Returns `other - self`.
+    fn offset_to(self, other: Self) -> i128;
+}
+
+macro_rules! impl_discrete {
+    ($($t:ty),*) => {
+        $(
+            impl Discrete for $t {
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn checked_shift(self, delta: i128) -> Option<Self> {
+                    (self as i128).checked_add(delta).and_then(|v| <$t>::try_from(v).ok())
+                }
+
+                fn offset_to(self, other: Self) -> i128 {
+                    other as i128 - self as i128
+                }
+            }
+        )*
+    };
+}
+
+impl_discrete!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// Interval is the inclusive range `[start, end]`. It is never empty.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Discrete> Interval<T> {
+    /// Returns `[start, end]`. Panics if `start > end`, see `try_new` for a fallible version.
+    pub fn new(start: T, end: T) -> Self {
+        Self::try_new(start, end).unwrap_or_else(|| panic!("interval start {start} is after its end {end}"))
+    }
+
+    pub fn try_new(start: T, end: T) -> Option<Self> {
+        (start <= end).then_some(Interval { start, end })
+    }
+
+    /// Returns `[start, start + len - 1]`, or None if the length is zero or the end doesn't fit.
+    pub fn with_len(start: T, len: u128) -> Option<Self> {
+        let last = i128::try_from(len.checked_sub(1)?).ok()?;
+        Some(Interval { start, end: start.checked_shift(last)? })
+    }
+
+    pub fn point(value: T) -> Self {
+        Interval { start: value, end: value }
+    }
+
+    /// Returns the interval holding every value of the type.
+    pub fn full() -> Self {
+        Interval { start: T::MIN, end: T::MAX }
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    /// Returns the number of values in the interval. The full range of a 64-bit type doesn't fit
+    /// in 64 bits, hence the u128.
+    pub fn len(&self) -> u128 {
+        self.start.offset_to(self.end) as u128 + 1
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        Self::try_new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// Returns whether the two intervals overlap or one starts right after the other ends, in
+    /// which case they can be merged into one.
+    fn touches(&self, other: &Self) -> bool {
+        self.overlaps(other)
+            || self.end.checked_shift(1) == Some(other.start)
+            || other.end.checked_shift(1) == Some(self.start)
+    }
+
+    /// Moves both ends by delta. Returns None if either end no longer fits in the type.
+    pub fn shift(&self, delta: i128) -> Option<Self> {
+        Some(Interval {
+            start: self.start.checked_shift(delta)?,
+            end: self.end.checked_shift(delta)?,
+        })
+    }
+}
+
+impl<T: Discrete> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.start, self.end)
+    }
+}
+
+/// IntervalSet is a set of values stored as the smallest list of intervals that covers them. The
+/// intervals are kept sorted, and overlapping or adjacent ones are merged, so two sets holding the
+/// same values are always equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Discrete> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet { intervals: vec![] }
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        &self.intervals
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&Interval<T>> + '_ {
+        self.intervals.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Returns the smallest value of the set.
+    pub fn min(&self) -> Option<T> {
+        self.intervals.first().map(|i| i.start)
+    }
+
+    /// Returns the largest value of the set.
+    pub fn max(&self) -> Option<T> {
+        self.intervals.last().map(|i| i.end)
+    }
+
+    /// Returns the number of values in the set.
+    pub fn len(&self) -> u128 {
+        self.intervals.iter().map(|i| i.len()).sum()
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let idx = self.intervals.partition_point(|i| i.end < value);
+        self.intervals.get(idx).is_some_and(|i| i.contains(value))
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        self.intervals.push(interval);
+        self.normalize();
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.iter().chain(other.iter()).copied().collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut out = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            out.extend(a.intersect(&b));
+            // Whichever interval ends first can't meet any later interval of the other set.
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        // The pieces are sorted and disjoint, but pieces cut from neighbouring intervals may be
+        // adjacent.
+        out.into_iter().collect()
+    }
+
+    /// Returns every value of the type that isn't in the set.
+    pub fn complement(&self) -> Self {
+        let mut out = vec![];
+        let mut next = Some(T::MIN);
+        for interval in self.intervals.iter() {
+            if let Some(start) = next {
+                if start < interval.start {
+                    out.push(Interval::new(start, interval.start.checked_shift(-1).unwrap()));
+                }
+            }
+            next = interval.end.checked_shift(1);
+        }
+        if let Some(start) = next {
+            out.push(Interval::new(start, T::MAX));
+        }
+        IntervalSet { intervals: out }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.intersection(&other.complement())
+    }
+
+    fn normalize(&mut self) {
+        self.intervals.sort();
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(self.intervals.len());
+        for interval in self.intervals.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.touches(&interval) => last.end = last.end.max(interval.end),
+                _ => merged.push(interval),
+            }
+        }
+        self.intervals = merged;
+    }
+}
+
+impl<T: Discrete> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Discrete> From<Interval<T>> for IntervalSet<T> {
+    fn from(interval: Interval<T>) -> Self {
+        IntervalSet { intervals: vec![interval] }
+    }
+}
+
+impl<T: Discrete> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item=Interval<T>>>(iter: I) -> Self {
+        let mut set = IntervalSet { intervals: iter.into_iter().collect() };
+        set.normalize();
+        set
+    }
+}
+
+/// PiecewiseShift is the function that adds a fixed offset to the values of each of its pieces,
+/// and maps every value outside of the pieces to itself:
+///
+///   f(x) = x + d₁ if x in [a₁, b₁], x + d₂ if x in [a₂, b₂], ..., x otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseShift<T> {
+    // Sorted by start and disjoint.
+    pieces: Vec<(Interval<T>, i128)>,
+}
+
+impl<T: Discrete> PiecewiseShift<T> {
+    /// Builds the function from its pieces, in any order. Pieces can't overlap, since a value
+    /// would then have two images.
+    pub fn new(pieces: impl IntoIterator<Item=(Interval<T>, i128)>) -> Result<Self, OverlapError<T>> {
+        let mut pieces = pieces.into_iter().collect::<Vec<_>>();
+        pieces.sort_by_key(|(interval, _)| *interval);
+        for pair in pieces.windows(2) {
+            let ((first, _), (second, _)) = (pair[0], pair[1]);
+            if first.overlaps(&second) {
+                return Err(OverlapError { first, second });
+            }
+        }
+        Ok(PiecewiseShift { pieces })
+    }
+
+    /// Returns the function that maps every value to itself.
+    pub fn identity() -> Self {
+        PiecewiseShift { pieces: vec![] }
+    }
+
+    pub fn pieces(&self) -> &[(Interval<T>, i128)] {
+        &self.pieces
+    }
+
+    /// Returns the image of a single value, or None if it doesn't fit in the type.
+    pub fn apply(&self, value: T) -> Option<T> {
+        let idx = self.pieces.partition_point(|(interval, _)| interval.end < value);
+        match self.pieces.get(idx) {
+            Some((interval, delta)) if interval.contains(value) => value.checked_shift(*delta),
+            _ => Some(value),
+        }
+    }
+
+    /// Returns the image of an interval. The interval is split wherever it crosses the edge of a
+    /// piece, so the image is made of one interval per piece it touches plus the parts that fall
+    /// between pieces. Returns None if any of them doesn't fit in the type.
+    pub fn apply_interval(&self, interval: Interval<T>) -> Option<Vec<Interval<T>>> {
+        self.split(interval)
+            .into_iter()
+            .map(|(part, delta)| part.shift(delta))
+            .collect()
+    }
+
+    /// Splits an interval wherever it crosses the edge of a piece and returns the parts in order,
+    /// each with the offset this function adds to it. Parts between pieces get an offset of 0.
+    pub fn split(&self, interval: Interval<T>) -> Vec<(Interval<T>, i128)> {
+        let mut out = vec![];
+        let mut remaining = Some(interval);
+        let first = self.pieces.partition_point(|(piece, _)| piece.end < interval.start);
+        for (piece, delta) in self.pieces[first..].iter() {
+            let Some(rest) = remaining else {
+                break;
+            };
+            if piece.start > rest.end {
+                break;
+            }
+            if rest.start < piece.start {
+                out.push((Interval::new(rest.start, piece.start.checked_shift(-1).unwrap()), 0));
+            }
+            let overlap = rest.intersect(piece).unwrap();
+            out.push((overlap, *delta));
+            remaining = (overlap.end < rest.end).then(|| Interval::new(overlap.end.checked_shift(1).unwrap(), rest.end));
+        }
+        out.extend(remaining.map(|rest| (rest, 0)));
+        out
+    }
+
+    /// Returns the function that applies this one and then `next`, as a single PiecewiseShift:
+    /// every piece of this function is pushed through `next` and split wherever its image crosses
+    /// the edge of one of `next`'s pieces. Neighbouring pieces that end up with the same offset
+    /// are merged, and pieces with no offset are left out since they map to themselves. Returns
+    /// None if this function takes a value out of the type.
+    pub fn then(&self, next: &Self) -> Option<Self> {
+        let mut pieces: Vec<(Interval<T>, i128)> = vec![];
+        // Parts come out in ascending order, both within the split of an image and across the
+        // split of the whole domain, so the pieces are built already sorted.
+        for (part, delta) in self.split(Interval::full()) {
+            for (image_part, next_delta) in next.split(part.shift(delta)?) {
+                let total = delta + next_delta;
+                if total == 0 {
+                    continue;
+                }
+                let domain = image_part.shift(-delta).unwrap();
+                match pieces.last_mut() {
+                    Some((last, last_delta)) if *last_delta == total && last.touches(&domain) => last.end = domain.end,
+                    _ => pieces.push((domain, total)),
+                }
+            }
+        }
+        Some(PiecewiseShift { pieces })
+    }
+
+    /// Returns the image of every value in the set.
+    pub fn apply_set(&self, set: &IntervalSet<T>) -> Option<IntervalSet<T>> {
+        let mut out = vec![];
+        for interval in set.iter() {
+            out.extend(self.apply_interval(*interval)?);
+        }
+        Some(out.into_iter().collect())
+    }
+
+    /// Returns every value whose image is in the set. Unlike `inverse`, this works whether or not
+    /// the function is a bijection.
+    pub fn preimage_set(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut out = vec![];
+        for (part, delta) in self.split(Interval::full()) {
+            // Values that would be shifted out of the type have no image, so the image is clamped
+            // to the type, and skipped if nothing is left of it.
+            let (start, end) = match (part.start.checked_shift(delta), part.end.checked_shift(delta)) {
+                (Some(start), Some(end)) => (start, end),
+                (None, Some(end)) if delta < 0 => (T::MIN, end),
+                (Some(start), None) if delta > 0 => (start, T::MAX),
+                _ => continue,
+            };
+            let image = Interval::new(start, end);
+            for target in set.iter() {
+                if let Some(hit) = image.intersect(target) {
+                    out.push(hit.shift(-delta).unwrap());
+                }
+            }
+        }
+        out.into_iter().collect()
+    }
+
+    /// Returns the function that undoes this one, or None if this function isn't a bijection of
+    /// the type, i.e. if two values have the same image or a value is shifted out of the type.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut images = vec![];
+        for (part, delta) in self.split(Interval::full()) {
+            images.push((part.shift(delta)?, delta));
+        }
+        images.sort_by_key(|(image, _)| *image);
+        // The images have as many values as the type between them, so they cover every value
+        // exactly once if and only if none of them overlap.
+        if images.windows(2).any(|pair| pair[0].0.overlaps(&pair[1].0)) {
+            return None;
+        }
+        let pieces = images.into_iter().filter(|(_, delta)| *delta != 0).map(|(image, delta)| (image, -delta));
+        Some(PiecewiseShift { pieces: pieces.collect() })
+    }
+}
+
+/// Prints one piece per line along with the offset it adds, e.g. `[50, 97] +2`.
+impl<T: Discrete> fmt::Display for PiecewiseShift<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (interval, delta) in self.pieces.iter() {
+            writeln!(f, "{interval} {delta:+}")?;
+        }
+        Ok(())
+    }
+}
+
+/// OverlapError is returned when two pieces of a PiecewiseShift overlap.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OverlapError<T> {
+    pub first: Interval<T>,
+    pub second: Interval<T>,
+}
+
+impl<T: Discrete> fmt::Display for OverlapError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ranges {} and {} overlap", self.first, self.second)
+    }
+}
+
+impl<T: Discrete> std::error::Error for OverlapError<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Rng;
+
+    fn set(intervals: &[(i32, i32)]) -> IntervalSet<i32> {
+        intervals.iter().map(|&(start, end)| Interval::new(start, end)).collect()
+    }
+
+    #[test]
+    fn test_interval() {
+        let a = Interval::new(1, 5);
+        assert_eq!(a.len(), 5);
+        assert!(a.contains(1) && a.contains(5) && !a.contains(6));
+        assert_eq!(a.intersect(&Interval::new(5, 9)), Some(Interval::point(5)));
+        assert_eq!(a.intersect(&Interval::new(6, 9)), None);
+        assert_eq!(Interval::try_new(2, 1), None);
+        assert_eq!(Interval::with_len(10u8, 3), Some(Interval::new(10, 12)));
+        assert_eq!(Interval::with_len(10u8, 0), None);
+        assert_eq!(Interval::with_len(250u8, 7), None);
+        assert_eq!(Interval::<u64>::full().len(), u64::MAX as u128 + 1);
+        assert_eq!(a.to_string(), "[1, 5]");
+    }
+
+    #[test]
+    fn test_shift_is_checked() {
+        assert_eq!(Interval::new(5usize, 10).shift(-5), Some(Interval::new(0, 10 - 5)));
+        assert_eq!(Interval::new(5usize, 10).shift(-6), None);
+        assert_eq!(Interval::new(0, usize::MAX - 1).shift(1), Some(Interval::new(1, usize::MAX)));
+        assert_eq!(Interval::new(0, usize::MAX).shift(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "interval start 3 is after its end 2")]
+    fn test_new_rejects_reversed_bounds() {
+        Interval::new(3, 2);
+    }
+
+    #[test]
+    fn test_set_normalizes() {
+        let s = set(&[(10, 12), (1, 3), (4, 5), (2, 2), (11, 20), (30, 30)]);
+        assert_eq!(s.intervals(), set(&[(1, 5), (10, 20), (30, 30)]).intervals());
+        assert_eq!(s.intervals().len(), 3);
+        assert_eq!(s.len(), 5 + 11 + 1);
+        assert_eq!((s.min(), s.max()), (Some(1), Some(30)));
+        assert!(s.contains(15) && !s.contains(25));
+
+        let mut s = s;
+        s.insert(Interval::new(21, 29));
+        assert_eq!(s, set(&[(1, 5), (10, 30)]));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set(&[(1, 10), (20, 30)]);
+        let b = set(&[(5, 25), (40, 40)]);
+        assert_eq!(a.union(&b), set(&[(1, 30), (40, 40)]));
+        assert_eq!(a.intersection(&b), set(&[(5, 10), (20, 25)]));
+        assert_eq!(a.difference(&b), set(&[(1, 4), (26, 30)]));
+        assert_eq!(b.difference(&a), set(&[(11, 19), (40, 40)]));
+        assert_eq!(set(&[(5, 10), (20, 30)]).intersection(&set(&[(0, 40)])), set(&[(5, 10), (20, 30)]));
+        assert_eq!(set(&[(1, 5)]).intersection(&set(&[(6, 9)])), IntervalSet::new());
+    }
+
+    #[test]
+    fn test_complement() {
+        assert_eq!(set(&[(1, 10), (20, 30)]).complement(), set(&[(i32::MIN, 0), (11, 19), (31, i32::MAX)]));
+        assert_eq!(IntervalSet::<i32>::new().complement(), IntervalSet::from(Interval::full()));
+        assert_eq!(IntervalSet::from(Interval::<u8>::full()).complement(), IntervalSet::new());
+        assert_eq!(IntervalSet::from(Interval::new(0u8, 10)).complement(), IntervalSet::from(Interval::new(11, 255)));
+    }
+
+    #[test]
+    fn test_set_identities() {
+        // Check a few identities of set algebra on every small set of small intervals.
+        let intervals = (0..8).flat_map(|a| (a..8).map(move |b| (a, b))).collect::<Vec<_>>();
+        let sets = intervals
+            .iter()
+            .flat_map(|&x| intervals.iter().map(move |&(c, d)| set(&[x, (c + 3, d + 3)])))
+            .collect::<Vec<_>>();
+        for a in sets.iter().step_by(7) {
+            for b in sets.iter().step_by(11) {
+                let union = a.union(b);
+                let intersection = a.intersection(b);
+                assert_eq!(union.len(), a.len() + b.len() - intersection.len());
+                assert_eq!(a.difference(b).union(&intersection), *a);
+                assert_eq!(union.complement(), a.complement().intersection(&b.complement()));
+                for v in -1..14 {
+                    assert_eq!(union.contains(v), a.contains(v) || b.contains(v));
+                    assert_eq!(intersection.contains(v), a.contains(v) && b.contains(v));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_piecewise_shift() {
+        let f = PiecewiseShift::new(vec![(Interval::new(50usize, 97), 2), (Interval::new(98, 99), -48)]).unwrap();
+        assert_eq!(f.apply(10), Some(10));
+        assert_eq!(f.apply(50), Some(52));
+        assert_eq!(f.apply(99), Some(51));
+        assert_eq!(f.apply(100), Some(100));
+        assert_eq!(
+            f.apply_interval(Interval::new(40, 120)),
+            Some(vec![Interval::new(40, 49), Interval::new(52, 99), Interval::new(50, 51), Interval::new(100, 120)])
+        );
+        assert_eq!(f.apply_interval(Interval::new(60, 70)), Some(vec![Interval::new(62, 72)]));
+        assert_eq!(
+            f.apply_set(&IntervalSet::from(Interval::new(40, 120))),
+            Some(IntervalSet::from(Interval::new(40, 120)))
+        );
+        assert_eq!(PiecewiseShift::identity().apply_interval(Interval::new(3, 4)), Some(vec![Interval::new(3, 4)]));
+    }
+
+    #[test]
+    fn test_then() {
+        let f = PiecewiseShift::new(vec![(Interval::new(0u8, 9), 10), (Interval::new(10, 19), -10)]).unwrap();
+        let g = PiecewiseShift::new(vec![(Interval::new(5u8, 14), 100)]).unwrap();
+        let h = f.then(&g).unwrap();
+        assert_eq!(
+            h.pieces(),
+            &[
+                (Interval::new(0, 4), 110),
+                (Interval::new(5, 9), 10),
+                (Interval::new(10, 14), -10),
+                (Interval::new(15, 19), 90),
+            ]
+        );
+        // Applying a function and then its mirror is the identity.
+        assert_eq!(f.then(&f).unwrap(), PiecewiseShift::identity());
+        assert_eq!(h.to_string(), "[0, 4] +110\n[5, 9] +10\n[10, 14] -10\n[15, 19] +90\n");
+        // Composing must agree with applying one function after the other on every value.
+        for x in 0..=u8::MAX {
+            assert_eq!(h.apply(x), f.apply(x).and_then(|y| g.apply(y)), "{x}");
+        }
+        // Neighbouring pieces that end up with the same offset are merged.
+        let f = PiecewiseShift::new(vec![(Interval::new(0u8, 4), 1), (Interval::new(5, 9), 2)]).unwrap();
+        let g = PiecewiseShift::new(vec![(Interval::new(6u8, 10), -1)]).unwrap();
+        assert_eq!(
+            f.then(&g).unwrap().pieces(),
+            &[(Interval::new(0, 8), 1), (Interval::new(9, 9), 2), (Interval::new(10, 10), -1)]
+        );
+
+        let overflowing = PiecewiseShift::new(vec![(Interval::new(250u8, 255), 1)]).unwrap();
+        assert_eq!(overflowing.then(&g), None);
+    }
+
+    #[test]
+    fn test_inverse_and_preimage() {
+        // Swaps [0, 9] and [10, 19].
+        let f = PiecewiseShift::new(vec![(Interval::new(0u8, 9), 10), (Interval::new(10, 19), -10)]).unwrap();
+        let g = PiecewiseShift::new(vec![(Interval::new(0u8, 4), 3), (Interval::new(5, 7), -5)]).unwrap();
+        for h in [&f, &g] {
+            let inverse = h.inverse().unwrap();
+            assert_eq!(h.then(&inverse).unwrap(), PiecewiseShift::identity());
+            assert_eq!(inverse.then(h).unwrap(), PiecewiseShift::identity());
+        }
+        assert_eq!(
+            g.preimage_set(&IntervalSet::from(Interval::new(0, 3))),
+            [Interval::new(0, 0), Interval::new(5, 7)].into_iter().collect()
+        );
+
+        // [0, 4] and [5, 9] both land on [5, 9].
+        let not_injective = PiecewiseShift::new(vec![(Interval::new(0u8, 4), 5)]).unwrap();
+        assert_eq!(not_injective.inverse(), None);
+        assert_eq!(
+            not_injective.preimage_set(&IntervalSet::from(Interval::new(6, 6))),
+            [Interval::point(1), Interval::point(6)].into_iter().collect()
+        );
+        let overflowing = PiecewiseShift::new(vec![(Interval::new(250u8, 255), 1)]).unwrap();
+        assert_eq!(overflowing.inverse(), None);
+        assert_eq!(overflowing.preimage_set(&IntervalSet::from(Interval::new(250, 255))), Interval::new(250, 254).into());
+        let gone = PiecewiseShift::new(vec![(Interval::new(200u8, 255), 100)]).unwrap();
+        assert_eq!(gone.preimage_set(&IntervalSet::from(Interval::full())), Interval::new(0, 199).into());
+    }
+
+    #[test]
+    fn test_apply_interval_matches_apply() {
+        // On random functions over u8, the image of an interval holds exactly the images of its
+        // values, or is None as soon as one of them is shifted out of the type.
+        let mut rng = Rng::new(12);
+        for _ in 0..2000 {
+            let pieces = (0..rng.below(4)).map(|_| {
+                let start = rng.below(256) as u8;
+                let len = rng.below(40) as u128 + 1;
+                let delta = rng.below(161) as i128 - 80;
+                (Interval::with_len(start, len).unwrap_or(Interval::new(start, u8::MAX)), delta)
+            });
+            let Ok(f) = PiecewiseShift::new(pieces.collect::<Vec<_>>()) else {
+                continue;
+            };
+            let start = rng.below(256) as u8;
+            let interval = Interval::with_len(start, rng.below(80) as u128 + 1).unwrap_or(Interval::new(start, u8::MAX));
+
+            let parts = f.split(interval);
+            assert_eq!(parts.iter().map(|(part, _)| part.len()).sum::<u128>(), interval.len());
+            assert!(parts.windows(2).all(|pair| pair[0].0.end.checked_shift(1) == Some(pair[1].0.start)));
+
+            let expected = (interval.start..=interval.end).map(|x| f.apply(x)).collect::<Option<Vec<_>>>();
+            match (f.apply_interval(interval), expected) {
+                (Some(image), Some(mut values)) => {
+                    let mut image_values = image.iter().flat_map(|i| i.start..=i.end).collect::<Vec<_>>();
+                    image_values.sort();
+                    values.sort();
+                    assert_eq!(image_values, values, "{f} {interval}");
+                }
+                (None, None) => {}
+                (image, values) => panic!("{f} {interval}: {image:?} but values {values:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_piecewise_shift_overflow_and_overlap() {
+        let f = PiecewiseShift::new(vec![(Interval::new(0usize, 10), -1)]).unwrap();
+        assert_eq!(f.apply(0), None);
+        assert_eq!(f.apply_interval(Interval::new(0, 20)), None);
+        let err = PiecewiseShift::new(vec![(Interval::new(5, 10), 1), (Interval::new(0, 5), 1)]).unwrap_err();
+        assert_eq!(err.to_string(), "ranges [0, 5] and [5, 10] overlap");
+    }
+}
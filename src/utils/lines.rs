@@ -1,12 +1,100 @@
-use std::io::BufRead;
-
-/// Returns a vector of String. The idea is to use this format to consume lines
-/// from the files. We can also mock this out by passing Vec<String> to the solutions
-/// since they expect this format as well.
-pub fn lines_from_file(filename: impl AsRef<std::path::Path>) -> Vec<String> {
-    let file = std::fs::File::open(filename).expect("no such file");
-    let buf = std::io::BufReader::new(file);
-    buf.lines()
-        .map(|l| l.expect("Could not parse line"))
-        .collect()
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::solver::Part;
+
+/// Example inputs copied from the puzzle text live here. A day whose parts use different examples
+/// has a `-part2` file next to the shared one.
+pub const EXAMPLES_DIR: &str = "inputs/examples";
+
+/// InputSource says where a solver's input should be read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputSource {
+    /// The personal puzzle input under ./inputs.
+    Personal,
+    /// The example from the puzzle text under ./inputs/examples.
+    Example,
+    File(PathBuf),
+    Stdin,
+}
+
+impl InputSource {
+    /// Returns the file the given part reads its input from, or None when it comes from stdin.
+    pub fn path(&self, year: usize, day: u8, part: Part) -> Option<PathBuf> {
+        match self {
+            InputSource::Personal => Some(default_input_path(year, day)),
+            InputSource::Example => Some(example_path(year, day, part)),
+            InputSource::File(path) => Some(path.clone()),
+            InputSource::Stdin => None,
+        }
+    }
+
+    pub fn describe(&self, year: usize, day: u8, part: Part) -> String {
+        self.path(year, day, part)
+            .map_or("stdin".to_string(), |path| path.display().to_string())
+    }
+
+    /// Reads the input of the given part.
+    pub fn read(&self, year: usize, day: u8, part: Part) -> std::io::Result<String> {
+        match self.path(year, day, part) {
+            Some(path) => input_from_file(path),
+            None => input_from_stdin(),
+        }
+    }
+}
+
+/// Reads the whole puzzle input. Solvers receive the input as a single &str and split it into
+/// lines themselves while parsing, which also lets tests pass string literals directly.
+pub fn input_from_file(filename: impl AsRef<std::path::Path>) -> std::io::Result<String> {
+    std::fs::read_to_string(filename)
+}
+
+pub fn input_from_stdin() -> std::io::Result<String> {
+    let mut contents = String::new();
+    std::io::stdin().read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Returns where the personal puzzle input of a day is expected to live.
+pub fn default_input_path(year: usize, day: u8) -> PathBuf {
+    format!("./inputs/{:04}-day{:02}.txt", year, day).into()
+}
+
+/// Returns the example for the given part, falling back to the example shared by both parts.
+pub fn example_path(year: usize, day: u8, part: Part) -> PathBuf {
+    example_path_in(Path::new("."), year, day, part)
+}
+
+/// Like example_path, but looks for the examples relative to `root` instead of the working
+/// directory.
+pub fn example_path_in(root: &Path, year: usize, day: u8, part: Part) -> PathBuf {
+    let examples = root.join(EXAMPLES_DIR);
+    let for_part = examples.join(format!("{:04}-day{:02}-part{part}.txt", year, day));
+    if for_part.exists() {
+        return for_part;
+    }
+    examples.join(format!("{:04}-day{:02}.txt", year, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_source_paths() {
+        assert_eq!(
+            InputSource::Personal.path(2023, 5, Part::One),
+            Some(PathBuf::from("./inputs/2023-day05.txt"))
+        );
+        assert_eq!(InputSource::Stdin.path(2023, 5, Part::One), None);
+        assert_eq!(InputSource::Stdin.describe(2023, 5, Part::One), "stdin");
+        // Day 8 has a different example for each part, day 5 shares one.
+        assert_eq!(
+            InputSource::Example.path(2023, 8, Part::Two),
+            Some(PathBuf::from("./inputs/examples/2023-day08-part2.txt"))
+        );
+        assert_eq!(
+            InputSource::Example.path(2023, 5, Part::Two),
+            Some(PathBuf::from("./inputs/examples/2023-day05.txt"))
+        );
+    }
 }
@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+
+/// Matcher finds every occurrence of a set of patterns in a single pass over the text, using an
+/// Aho-Corasick automaton built once. Each pattern carries a value that is handed back with its
+/// matches, e.g. the digit a spelled out number stands for. Positions are byte offsets.
+#[derive(Debug, Clone)]
+pub struct Matcher<V> {
+    patterns: Vec<(Vec<u8>, V)>,
+    forward: Automaton,
+    /// The automaton of the reversed patterns, to scan the text from its end.
+    backward: Automaton,
+    longest: usize,
+}
+
+/// Match is an occurrence of a pattern at `start..end` in the text.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Match<'a, V> {
+    pub start: usize,
+    pub end: usize,
+    pub value: &'a V,
+}
+
+impl<V> Matcher<V> {
+    /// Builds the matcher of the given patterns. Panics if a pattern is empty, since it would
+    /// match everywhere.
+    pub fn new<P: AsRef<[u8]>>(patterns: impl IntoIterator<Item=(P, V)>) -> Self {
+        let patterns = patterns
+            .into_iter()
+            .map(|(pattern, value)| (pattern.as_ref().to_vec(), value))
+            .collect::<Vec<_>>();
+        assert!(patterns.iter().all(|(pattern, _)| !pattern.is_empty()), "patterns can't be empty");
+        Matcher {
+            forward: Automaton::new(patterns.iter().map(|(pattern, _)| pattern.iter().copied())),
+            backward: Automaton::new(patterns.iter().map(|(pattern, _)| pattern.iter().rev().copied())),
+            longest: patterns.iter().map(|(pattern, _)| pattern.len()).max().unwrap_or(0),
+            patterns,
+        }
+    }
+
+    /// Returns every match, overlapping ones included, ordered by where they end. Matches that
+    /// end at the same place come longest first.
+    pub fn find_iter<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item=Match<'a, V>> + 'a {
+        haystack
+            .iter()
+            .scan(0, |state, &byte| {
+                *state = self.forward.step(*state, byte);
+                Some(*state)
+            })
+            .enumerate()
+            .flat_map(move |(idx, state)| {
+                self.forward.outputs[state]
+                    .iter()
+                    .map(move |&pattern| self.found(pattern, idx + 1 - self.patterns[pattern].0.len()))
+            })
+    }
+
+    /// Returns the match that starts first, the longest one if several start there.
+    pub fn first<'a>(&'a self, haystack: &'a [u8]) -> Option<Match<'a, V>> {
+        let mut first: Option<Match<V>> = None;
+        for found in self.find_iter(haystack) {
+            if let Some(best) = &first {
+                // No match ending this far can start before the best one.
+                if found.end > best.start + self.longest {
+                    break;
+                }
+                if found.start > best.start || (found.start == best.start && found.end <= best.end) {
+                    continue;
+                }
+            }
+            first = Some(found);
+        }
+        first
+    }
+
+    /// Returns the match that starts last, the longest one if several start there. The text is
+    /// scanned from its end, so only what follows the match is read.
+    pub fn last<'a>(&'a self, haystack: &'a [u8]) -> Option<Match<'a, V>> {
+        let mut state = 0;
+        for (idx, &byte) in haystack.iter().enumerate().rev() {
+            state = self.backward.step(state, byte);
+            if let Some(&pattern) = self.backward.outputs[state].first() {
+                return Some(self.found(pattern, idx));
+            }
+        }
+        None
+    }
+
+    fn found(&self, pattern: usize, start: usize) -> Match<'_, V> {
+        let (bytes, value) = &self.patterns[pattern];
+        Match {
+            start,
+            end: start + bytes.len(),
+            value,
+        }
+    }
+}
+
+/// Automaton has a state for every prefix of the patterns and a transition for every byte, so
+/// scanning reads each byte once without ever backtracking. State 0 is the empty prefix.
+#[derive(Debug, Clone)]
+struct Automaton {
+    next: Vec<[usize; 256]>,
+    /// The patterns that end on reaching each state, longest first.
+    outputs: Vec<Vec<usize>>,
+}
+
+impl Automaton {
+    fn new(patterns: impl Iterator<Item=impl Iterator<Item=u8>>) -> Self {
+        // Start with the trie of the patterns, where 0 stands for a missing transition since
+        // nothing leads back to the root.
+        let mut next = vec![[0; 256]];
+        let mut outputs = vec![vec![]];
+        for (idx, pattern) in patterns.enumerate() {
+            let mut state = 0;
+            for byte in pattern {
+                if next[state][byte as usize] == 0 {
+                    next.push([0; 256]);
+                    outputs.push(vec![]);
+                    next[state][byte as usize] = next.len() - 1;
+                }
+                state = next[state][byte as usize];
+            }
+            outputs[state].push(idx);
+        }
+
+        // Then visit the states from the shortest prefix to the longest. A missing transition
+        // goes where the transition of the longest proper suffix that is also a prefix goes,
+        // which is complete by then, and the patterns ending on that suffix end here too.
+        let mut suffix = vec![0; next.len()];
+        let mut queue = VecDeque::from([0]);
+        while let Some(state) = queue.pop_front() {
+            if state != 0 {
+                let inherited = outputs[suffix[state]].clone();
+                outputs[state].extend(inherited);
+            }
+            let fallback = next[suffix[state]];
+            for (byte, child) in next[state].iter_mut().enumerate() {
+                if *child == 0 {
+                    *child = fallback[byte];
+                } else {
+                    suffix[*child] = if state == 0 { 0 } else { fallback[byte] };
+                    queue.push_back(*child);
+                }
+            }
+        }
+        Automaton { next, outputs }
+    }
+
+    fn step(&self, state: usize, byte: u8) -> usize {
+        self.next[state][byte as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans<'a, V: Copy + 'a>(matches: impl Iterator<Item=Match<'a, V>>) -> Vec<(usize, usize, V)> {
+        matches.map(|found| (found.start, found.end, *found.value)).collect()
+    }
+
+    #[test]
+    fn test_overlapping_matches() {
+        let matcher = Matcher::new([("eight", 8), ("two", 2), ("one", 1)]);
+        assert_eq!(spans(matcher.find_iter(b"eightwone")), vec![(0, 5, 8), (4, 7, 2), (6, 9, 1)]);
+        assert_eq!(spans(matcher.find_iter(b"eigh")), vec![]);
+        assert_eq!(spans(matcher.find_iter(b"")), vec![]);
+    }
+
+    #[test]
+    fn test_patterns_inside_patterns() {
+        let matcher = Matcher::new([("he", 'a'), ("she", 'b'), ("his", 'c'), ("hers", 'd')]);
+        assert_eq!(
+            spans(matcher.find_iter(b"ushers")),
+            vec![(1, 4, 'b'), (2, 4, 'a'), (2, 6, 'd')]
+        );
+        assert_eq!(spans(matcher.find_iter(b"ahishers")), vec![(1, 4, 'c'), (3, 6, 'b'), (4, 6, 'a'), (4, 8, 'd')]);
+    }
+
+    #[test]
+    fn test_first_and_last() {
+        // "bc" ends before "abcd" but starts after it.
+        let matcher = Matcher::new([("abcd", 1), ("bc", 2), ("cd", 3), ("d", 4)]);
+        assert_eq!(spans(matcher.first(b"xabcdx").into_iter()), vec![(1, 5, 1)]);
+        assert_eq!(spans(matcher.last(b"xabcdx").into_iter()), vec![(4, 5, 4)]);
+        assert_eq!(spans(matcher.last(b"xabcx").into_iter()), vec![(2, 4, 2)]);
+        assert_eq!(matcher.first(b"xyz"), None);
+        assert_eq!(matcher.last(b""), None);
+
+        // Ties on where a match starts go to the longest one.
+        let matcher = Matcher::new([("a", 1), ("ab", 2), ("b", 3)]);
+        assert_eq!(spans(matcher.first(b"ab").into_iter()), vec![(0, 2, 2)]);
+        assert_eq!(spans(matcher.last(b"aab").into_iter()), vec![(2, 3, 3)]);
+        assert_eq!(spans(matcher.last(b"aba").into_iter()), vec![(2, 3, 1)]);
+    }
+
+    #[test]
+    fn test_against_naive_search() {
+        let words = ["one", "two", "three", "eight", "nine", "ne", "e", "ee", "tw"];
+        let matcher = Matcher::new(words.iter().enumerate().map(|(idx, word)| (word, idx)));
+        let text = b"oneightwothreeeninetwonetweenine";
+        let mut expected = vec![];
+        for end in 1..=text.len() {
+            let mut ending_here = words
+                .iter()
+                .enumerate()
+                .filter(|(_, word)| text[..end].ends_with(word.as_bytes()))
+                .map(|(idx, word)| (end - word.len(), end, idx))
+                .collect::<Vec<_>>();
+            ending_here.sort();
+            expected.extend(ending_here);
+        }
+        assert_eq!(spans(matcher.find_iter(text)), expected);
+        assert_eq!(spans(matcher.first(text).into_iter()), vec![expected[0]]);
+        let last = expected.iter().max_by_key(|span| (span.0, span.1));
+        assert_eq!(spans(matcher.last(text).into_iter()), vec![*last.unwrap()]);
+    }
+
+    #[test]
+    fn test_multi_byte_patterns() {
+        let matcher = Matcher::new([("€", "euro"), ("é", "e acute")]);
+        let text = "prix: 5€, café".as_bytes();
+        assert_eq!(spans(matcher.find_iter(text)), vec![(7, 10, "euro"), (15, 17, "e acute")]);
+    }
+
+    #[test]
+    #[should_panic(expected = "patterns can't be empty")]
+    fn test_empty_pattern() {
+        Matcher::new([("", 0)]);
+    }
+}
@@ -1,2 +1,5 @@
+pub mod grid;
+pub mod interval;
 pub mod lines;
-pub mod parsers;
\ No newline at end of file
+pub mod matcher;
+pub mod parsers;
@@ -1,6 +1,7 @@
 use nom::bytes::complete::take_while1;
 use nom::combinator::map_res;
 use nom::IResult;
+use crate::solver::SolveError;
 
 pub fn parse_number(i: &str) -> IResult<&str, usize> {
     // take_while1 grabs as many numbers as it can
@@ -8,4 +9,48 @@ pub fn parse_number(i: &str) -> IResult<&str, usize> {
     map_res(take_while1(|c: char| c.is_ascii_digit()), |s: &str| {
         s.parse::<usize>()
     })(i)
-}
\ No newline at end of file
+}
+
+/// Runs a nom parser on a single input line and turns a failure into a SolveError that points at
+/// the line and the column where the parser gave up. Anything but whitespace left over after the
+/// parser is done is reported as an error as well.
+pub fn parse_line<'a, T>(
+    line_idx: usize,
+    line: &'a str,
+    context: &str,
+    mut parser: impl FnMut(&'a str) -> IResult<&'a str, T>,
+) -> Result<T, SolveError> {
+    match parser(line) {
+        Ok((rest, _)) if !rest.trim().is_empty() => {
+            let column = line.len() - rest.len() + 1;
+            Err(SolveError::new(format!("unexpected input at column {column}: {rest:?}"))
+                .with_line(line_idx)
+                .with_context(context))
+        }
+        Ok((_, value)) => Ok(value),
+        Err(err) => {
+            let message = match err {
+                nom::Err::Error(e) | nom::Err::Failure(e) => {
+                    let column = line.len() - e.input.len() + 1;
+                    format!("{:?} failed at column {column} near {:?}", e.code, e.input)
+                }
+                nom::Err::Incomplete(_) => "incomplete input".to_string(),
+            };
+            Err(SolveError::new(message).with_line(line_idx).with_context(context))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line(0, "123  ", "number", parse_number), Ok(123));
+        let err = parse_line(0, "123abc", "number", parse_number).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: number: unexpected input at column 4: \"abc\"");
+        let err = parse_line(4, "ab12", "number", parse_number).unwrap_err();
+        assert_eq!(err.to_string(), "line 5: number: TakeWhile1 failed at column 1 near \"ab12\"");
+    }
+}
@@ -1,206 +1,146 @@
 use crate::solver;
+use crate::solver::{Answer, SolveError};
+use crate::registry::SolverRegistry;
+use crate::utils::matcher::Matcher;
 
-pub struct Day1Solver {}
-
-impl solver::Solver for Day1Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String {
-        let mut digits: Vec<u32> = vec![];
-        for line in lines {
-            // If it's a digit push it, otherwise move on
-            let line_digits = line
-                .chars()
-                .filter_map(|c| c.to_digit(10))
-                .collect::<Vec<u32>>();
-            let l = line_digits.len();
-            digits.push(10 * line_digits[0] + line_digits[l - 1]);
-        }
-        digits.iter().sum::<u32>().to_string()
-    }
+pub struct Day1Solver {
+    /// Finds the digits of part 2, written with a digit or spelled out.
+    digits: Matcher<u32>,
+}
 
-    fn solve_part_2(&self, lines: Vec<String>) -> String {
-        let mut digits: Vec<u64> = vec![];
-        for line in lines {
-            // If it's a digit push it, otherwise move on
-            let mut line_digits = vec![];
-            let mut parser = NumberParser::new(&line);
-            while parser.has_next() {
-                if let Some(d) = parser.parse_at_current() {
-                    line_digits.push(d as u64);
-                }
-            }
-            let l = line_digits.len();
-            digits.push(10 * line_digits[0] + line_digits[l - 1]);
+impl Day1Solver {
+    pub fn new() -> Self {
+        let spelled = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+        let written = (0..=9).map(|digit| (digit.to_string(), digit));
+        let spelled = spelled.iter().zip(1..).map(|(word, digit)| (word.to_string(), digit));
+        Day1Solver {
+            digits: Matcher::new(written.chain(spelled)),
         }
-        digits.iter().sum::<u64>().to_string()
     }
 }
 
-struct NumberParser<'a> {
-    // input has the lifetime of the NumberParser
-    input: &'a str,
-    index: usize,
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 1, Box::new(Day1Solver::new()));
 }
 
-impl<'a> NumberParser<'a> {
-    fn new(input: &str) -> NumberParser {
-        NumberParser {
-            input,
-            index: 0,
-        }
-    }
+impl solver::Solver for Day1Solver {
+    type Input = Vec<String>;
 
-    fn has_next(&self) -> bool {
-        self.index < self.input.len()
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        Ok(input.lines().map(|line| line.to_string()).collect())
     }
 
-    fn parse_at_current(&mut self) -> Option<u8> {
-        if !self.has_next() {
-            return None;
-        }
-        let c = self.input.as_bytes()[self.index];
-        if c.is_ascii_digit() {
-            self.index += 1;
-            return Some(c - b'0');
+    fn solve_part_1(&self, lines: &Self::Input) -> Result<Answer, SolveError> {
+        let mut sum = 0;
+        for (idx, line) in lines.iter().enumerate() {
+            let first = line.chars().find_map(|c| c.to_digit(10));
+            let last = line.chars().rev().find_map(|c| c.to_digit(10));
+            sum += calibration_value(idx, first, last)?;
         }
-        let remaining_length = self.input.len() - self.index;
-        let candidates = std::collections::HashSet::from(
-            [
-                ("one", 1),
-                ("two", 2),
-                ("three", 3),
-                ("four", 4),
-                ("five", 5),
-                ("six", 6),
-                ("seven", 7),
-                ("eight", 8),
-                ("nine", 9),
-            ],
-        );
-        for candidate in candidates {
-            if candidate.0.len() <= remaining_length && &self.input[self.index..self.index + candidate.0.len()] == candidate.0 {
-                // Note that twone is two and then one, so we can't skip the length of the word
-                self.index += 1;
-                return Some(candidate.1);
-            }
+        Ok(sum.into())
+    }
+
+    fn solve_part_2(&self, lines: &Self::Input) -> Result<Answer, SolveError> {
+        let mut sum = 0;
+        for (idx, line) in lines.iter().enumerate() {
+            // Spelled out digits can share letters, like the 8 and 2 of "eightwo", so the last
+            // digit is searched from the end of the line instead of after the first one.
+            let first = self.digits.first(line.as_bytes()).map(|found| *found.value);
+            let last = self.digits.last(line.as_bytes()).map(|found| *found.value);
+            sum += calibration_value(idx, first, last)?;
         }
-        self.index += 1;
-        None
+        Ok(sum.into())
     }
 }
 
+/// The calibration value is made of the first and last digit found on the line.
+fn calibration_value(line_idx: usize, first: Option<u32>, last: Option<u32>) -> Result<u32, SolveError> {
+    match (first, last) {
+        (Some(first), Some(last)) => Ok(10 * first + last),
+        _ => Err(SolveError::new("no digits found").with_line(line_idx)),
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::solver::Solver;
-    use crate::utils::lines::lines_from_file;
+    use crate::solver::Part;
+    use crate::test_support::{example, require_input};
 
     #[test]
     fn test_part_1_unit() {
-        let solver = Day1Solver {};
-        let lines = vec!["1234".to_string()];
-        assert_eq!(solver.solve_part_1(lines), "14");
-        let lines = vec!["1xx4".to_string()];
-        assert_eq!(solver.solve_part_1(lines), "14");
-        let lines = vec!["1xxx".to_string()];
-        assert_eq!(solver.solve_part_1(lines), "11", "single number");
-        let lines = vec!["100000005x0".to_string()];
-        assert_eq!(solver.solve_part_1(lines), "10");
+        let solver = Day1Solver::new();
+        let input = solver.parse(&["1234"].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "14");
+        let input = solver.parse(&["1xx4"].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "14");
+        let input = solver.parse(&["1xxx"].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "11", "single number");
+        let input = solver.parse(&["100000005x0"].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "10");
     }
 
     #[test]
     fn test_part_1() {
-        let solver = Day1Solver {};
-        let lines = lines_from_file("./inputs/day01.txt");
-        assert_eq!(solver.solve_part_1(lines), "55002");
+        let solver = Day1Solver::new();
+        let input = solver.parse(&require_input!(2023, 1)).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "55002");
     }
 
     #[test]
     fn test_part_2_unit() {
-        let solver = Day1Solver {};
-        let lines = vec![
-            "two1nine".to_string(),
-            "eightwothree".to_string(),
-            "abcone2threexyz".to_string(),
-            "xtwone3four".to_string(),
-            "4nineeightseven2".to_string(),
-            "zoneight234".to_string(),
-            "7pqrstsixteen".to_string(),
-        ];
-        assert_eq!(solver.solve_part_2(lines), "281");
+        let solver = Day1Solver::new();
+        let input = solver.parse(&example(2023, 1, Part::Two)).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "281");
     }
 
     #[test]
     fn test_part_2_single_number() {
-        let solver = Day1Solver {};
-        let lines = vec![
-            "v4".to_string(),
-        ];
-        assert_eq!(solver.solve_part_2(lines), "44");
+        let solver = Day1Solver::new();
+        let input = solver.parse(&[
+            "v4",
+        ].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "44");
     }
 
     #[test]
-    fn test_part_2() {
-        let solver = Day1Solver {};
-        let lines = lines_from_file("./inputs/day01.txt");
-        assert_eq!(solver.solve_part_2(lines), "55093");
+    fn test_line_without_digits() {
+        let solver = Day1Solver::new();
+        let input = solver.parse(&["1abc2", "abc"].join("\n")).unwrap();
+        let err = solver.solve_part_1(&input).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: no digits found");
     }
 
     #[test]
-    fn test_number_parser() {
-        let mut parser = NumberParser::new("1234");
-        assert_eq!(parser.parse_at_current(), Some(1));
-        assert_eq!(parser.parse_at_current(), Some(2));
-        assert_eq!(parser.parse_at_current(), Some(3));
-        assert_eq!(parser.parse_at_current(), Some(4));
-        assert_eq!(parser.parse_at_current(), None);
+    fn test_part_2() {
+        let solver = Day1Solver::new();
+        let input = solver.parse(&require_input!(2023, 1)).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "55093");
     }
 
-    #[test]
-    fn test_number_parser_some_letters() {
-        let mut parser = NumberParser::new("1xx4");
-        assert_eq!(parser.parse_at_current(), Some(1));
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), Some(4));
-        assert_eq!(parser.parse_at_current(), None);
+    fn digits(line: &str) -> Vec<u32> {
+        let solver = Day1Solver::new();
+        solver.digits.find_iter(line.as_bytes()).map(|found| *found.value).collect()
     }
 
     #[test]
-    fn test_with_named_numbers() {
-        let mut parser = NumberParser::new("xonextwothree4xfiv6e");
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), Some(1));
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), Some(2));
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), Some(3));
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), Some(4));
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), None);
-        assert_eq!(parser.parse_at_current(), Some(6));
-        assert_eq!(parser.parse_at_current(), None);
+    fn test_digits() {
+        assert_eq!(digits("1234"), vec![1, 2, 3, 4]);
+        assert_eq!(digits("1xx4"), vec![1, 4]);
+        assert_eq!(digits("xonextwothree4xfiv6e"), vec![1, 2, 3, 4, 6]);
+        assert_eq!(digits("tsgbzmgbonethreedrqzbhxjkvcnm3"), vec![1, 3, 3]);
+        // Spelled out digits can overlap.
+        assert_eq!(digits("eightwone"), vec![8, 2, 1]);
     }
 
     #[test]
-    fn test_with_more_lines() {
-        let mut parser = NumberParser::new("tsgbzmgbonethreedrqzbhxjkvcnm3");
-        let mut actual_digits = vec![];
-        while parser.has_next() {
-            let val = parser.parse_at_current();
-            if val.is_some() {
-                actual_digits.push(val.unwrap());
-            }
-        }
-        assert_eq!(actual_digits, vec![1, 3, 3]);
+    fn test_overlapping_digits() {
+        let solver = Day1Solver::new();
+        let input = solver.parse(&["eightwo", "twone", "oneight", "sevenine2sevenine"].join("\n")).unwrap();
+        // 82 + 21 + 18 + 79
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "200");
+        let err = solver.solve_part_2(&solver.parse("eigh\nnin").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: no digits found");
     }
 }
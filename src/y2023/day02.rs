@@ -1,37 +1,52 @@
 use nom::bytes::complete::{tag, take_while1};
-use nom::combinator::{map, map_res, value};
+use nom::combinator::{map_res, value};
 use nom::IResult;
 use nom::multi::separated_list1;
 use crate::solver;
+use crate::solver::{Answer, SolveError};
+use crate::utils::parsers::parse_line;
+use crate::registry::SolverRegistry;
 
 
 pub struct Day2Solver {}
 
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 2, Box::new(Day2Solver {}));
+}
+
 impl solver::Solver for Day2Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String {
+    type Input = Vec<GameLine>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| parse_line(idx, line, "parsing game", parse_game_line))
+            .collect()
+    }
+
+    fn solve_part_1(&self, games: &Self::Input) -> Result<Answer, SolveError> {
         let max_reveal = RevealedDice {
             red: 12,
             blue: 14,
             green: 13,
         };
         let mut sum_of_valid_games = 0;
-        for line in lines {
-            let (_, game) = parse_game_line(&line).unwrap();
+        for game in games {
             if game.reveals_less_than(&max_reveal) {
                 sum_of_valid_games += game.id;
             }
         }
-        sum_of_valid_games.to_string()
+        Ok(sum_of_valid_games.into())
     }
 
-    fn solve_part_2(&self, lines: Vec<String>) -> String {
+    fn solve_part_2(&self, games: &Self::Input) -> Result<Answer, SolveError> {
         let mut sum_of_powers = 0usize;
-        for line in lines {
-            let (_, game) = parse_game_line(&line).unwrap();
+        for game in games {
             let min_dice = game.min_dice_required();
             sum_of_powers += min_dice.red as usize * min_dice.blue as usize * min_dice.green as usize;
         }
-        sum_of_powers.to_string()
+        Ok(sum_of_powers.into())
     }
 }
 
@@ -43,7 +58,7 @@ enum DieColor {
 }
 
 #[derive(Debug, Clone)]
-struct GameLine {
+pub struct GameLine {
     id: usize,
     game_reveals: Vec<RevealedDice>,
 }
@@ -122,7 +137,8 @@ fn parse_revealed_dice(line: &str) -> IResult<&str, RevealedDice> {
 }
 
 fn parse_die_colors(line: &str) -> IResult<&str, (u8, DieColor)> {
-    map(
+    // A count that doesn't fit in a u8 fails the line rather than wrapping around.
+    map_res(
         nom::sequence::tuple(
             (
                 parse_number,
@@ -130,8 +146,8 @@ fn parse_die_colors(line: &str) -> IResult<&str, (u8, DieColor)> {
                 parse_color,
             )
         )
-        , |(usize, _, color)| {
-            (usize as u8, color)
+        , |(count, _, color)| {
+            u8::try_from(count).map(|count| (count, color))
         })(line)
 }
 
@@ -157,39 +173,60 @@ fn parse_color(i: &str) -> IResult<&str, DieColor> {
 mod tests {
     use super::*;
     use crate::solver::Solver;
-    use crate::utils::lines::lines_from_file;
-    use crate::y2023::day01::Day1Solver;
+    use crate::test_support::require_input;
 
     #[test]
     fn test_part_1_unit_test() {
-        let lines = vec![
-            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green".to_string(),
-            "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue".to_string(),
-            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red".to_string(),
-            "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red".to_string(),
-            "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green   ".to_string(),
-        ];
         let solver = Day2Solver {};
-        assert_eq!(solver.solve_part_1(lines), "8");
+        let input = solver.parse(&[
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+            "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue",
+            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red",
+            "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red",
+            "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green   ",
+        ].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "8");
     }
 
     #[test]
     fn test_part_1() {
-        let solver = Day1Solver {};
-        let lines = lines_from_file("./inputs/day02.txt");
-        assert_eq!(solver.solve_part_1(lines), "2317");
+        let solver = Day2Solver {};
+        let input = solver.parse(&require_input!(2023, 2)).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "2317");
     }
 
     #[test]
     fn test_part_2_unit_test() {
-        let lines = vec![
-            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green".to_string(),
-            "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue".to_string(),
-            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red".to_string(),
-            "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red".to_string(),
-            "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green   ".to_string(),
-        ];
         let solver = Day2Solver {};
-        assert_eq!(solver.solve_part_2(lines), "2286");
+        let input = solver.parse(&[
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+            "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue",
+            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red",
+            "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red",
+            "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green   ",
+        ].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "2286");
+    }
+
+    #[test]
+    fn test_malformed_game() {
+        let solver = Day2Solver {};
+        let err = solver.parse(&[
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+            "Game 2: 1 blue, 2 purple",
+        ].join("\n")).unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.context.as_deref(), Some("parsing game"));
+    }
+
+    #[test]
+    fn test_count_too_large() {
+        let solver = Day2Solver {};
+        for game in ["Game 1: 300 red", "Game 1: 3 blue, 256 red; 1 green"] {
+            let err = solver.parse(game).unwrap_err();
+            assert_eq!(err.line, Some(1), "{game}");
+        }
+        let input = solver.parse("Game 1: 255 red").unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "0");
     }
 }
\ No newline at end of file
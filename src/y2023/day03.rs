@@ -1,139 +1,255 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use crate::solver;
+use crate::solver::{Answer, Discard, Event, EventSink, SolveError};
+use crate::registry::SolverRegistry;
+use crate::utils::grid::{Grid, Pos};
 
 pub struct Day3Solver {}
 
-struct Schema {
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 3, Box::new(Day3Solver {}));
+}
+
+#[derive(Debug)]
+pub struct Schema {
     parts: Vec<Part>,
     symbols: Vec<Symbol>,
-    symbol_locations: HashMap<isize, Vec<Symbol>>,
-    part_locations: HashMap<isize, Vec<Part>>,
+    /// The index in `parts` of the part drawn on each cell, so that finding the parts around a
+    /// cell only takes looking at its neighbours.
+    occupancy: Grid<Option<usize>>,
 }
 
 impl Schema {
-    fn parts_adjacent_to_point(&self, location: &Location) -> Vec<Part> {
-        let row = location.y;
-        let part_candidates: Vec<Part> = vec![
-            self.part_locations.get(&(row - 1)).cloned().unwrap_or(vec![]),
-            self.part_locations.get(&row).cloned().unwrap_or(vec![]),
-            self.part_locations.get(&(row + 1)).cloned().unwrap_or(vec![]),
-        ].into_iter().flatten().collect();
-
-        // The most number of parts that can be adjacent to a point is 8.
-        let mut adjacent_points = Vec::with_capacity(8);
-
-        for part in part_candidates.iter() {
-            if part.is_adjacent_to(location) {
-                adjacent_points.push(*part);
+    fn part_at(&self, location: Pos) -> Option<usize> {
+        self.occupancy.get(location).copied().flatten()
+    }
+
+    /// Returns the index of every part next to the location, each of them once, from left to
+    /// right and top to bottom.
+    fn parts_adjacent_to_point(&self, location: Pos) -> impl Iterator<Item=usize> + Clone + '_ {
+        let first_col = location.col.saturating_sub(1);
+        (location.row.saturating_sub(1)..=location.row + 1).flat_map(move |row| {
+            (first_col..=location.col + 1).filter_map(move |col| {
+                let part = self.part_at(Pos::new(row, col))?;
+                // A part takes consecutive cells of its row, so it was already returned when it
+                // also takes the cell to the left.
+                if col > first_col && self.part_at(Pos::new(row, col - 1)) == Some(part) {
+                    return None;
+                }
+                Some(part)
+            })
+        })
+    }
+
+    /// Returns, for every part, the first symbol next to it if there is one. Going from the
+    /// symbols to the parts around them only looks at the neighbours of each symbol.
+    fn adjacent_symbols(&self) -> Vec<Option<&str>> {
+        let mut adjacent_symbols = vec![None; self.parts.len()];
+        for symbol in self.symbols.iter() {
+            for part in self.parts_adjacent_to_point(symbol.location) {
+                adjacent_symbols[part].get_or_insert(symbol.symbol.as_str());
             }
         }
-        adjacent_points
+        adjacent_symbols
+    }
+
+    /// Returns the symbols drawn as `class` that are next to as many parts as `adjacency` asks
+    /// for, each with the indexes of those parts.
+    fn symbols_next_to_parts<'a>(
+        &'a self,
+        class: &'a str,
+        adjacency: Adjacency,
+    ) -> impl Iterator<Item=(&'a Symbol, impl Iterator<Item=usize> + Clone + 'a)> + 'a {
+        self.symbols
+            .iter()
+            .filter(move |symbol| symbol.symbol == class)
+            .map(|symbol| (symbol, self.parts_adjacent_to_point(symbol.location)))
+            .filter(move |(_, parts)| adjacency.accepts(parts.clone().count()))
     }
+
+    /// Returns the parts that no symbol is next to.
+    fn parts_without_symbols(&self) -> impl Iterator<Item=&Part> + '_ {
+        self.parts
+            .iter()
+            .zip(self.adjacent_symbols())
+            .filter_map(|(part, symbol)| symbol.is_none().then_some(part))
+    }
+
+    /// Counts how many times each symbol is drawn.
+    fn symbol_histogram(&self) -> BTreeMap<&str, usize> {
+        let mut histogram = BTreeMap::new();
+        for symbol in self.symbols.iter() {
+            *histogram.entry(symbol.symbol.as_str()).or_default() += 1;
+        }
+        histogram
+    }
+}
+
+/// Adjacency says how many parts a symbol has to be next to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Adjacency {
+    Exactly(usize),
+    AtLeast(usize),
 }
 
-fn schema_from_lines(lines: Vec<String>) -> Schema {
+impl Adjacency {
+    fn accepts(self, count: usize) -> bool {
+        match self {
+            Adjacency::Exactly(n) => count == n,
+            Adjacency::AtLeast(n) => count >= n,
+        }
+    }
+}
+
+/// Reads the schematic. Columns count characters as the grid reads them, so a symbol outside of
+/// ASCII takes a single column like any other, and so does one written with combining marks.
+/// Part numbers are made of ASCII digits only, other digits such as `٣` are rejected rather than
+/// read as symbols. Rows may be of different widths, the short ones read as if they ended in
+/// empty cells.
+fn schema_from_lines(input: &str) -> Result<Schema, SolveError> {
+    let grid = Grid::parse_padded(input, ".", |location, c| {
+        if c.starts_with(char::is_numeric) && !is_digit(c) {
+            return Err(SolveError::new(format!("{c:?} in column {} is not an ASCII digit", location.col + 1))
+                .with_context("parsing schematic"));
+        }
+        Ok(c)
+    })?;
     let mut parts = vec![];
     let mut symbols = vec![];
-    let mut part_locations: HashMap<isize, Vec<Part>> = HashMap::new();
-    let mut symbol_locations: HashMap<isize, Vec<Symbol>> = HashMap::new();
-    for (current_y, line) in lines.iter().enumerate() {
-        let current_y = current_y as isize;
-        let mut on_part = false;
-        let mut starting_idx = 0isize;
-        for (current_x, c) in line.chars().enumerate() {
-            let current_x = current_x as isize;
-            let is_number = c.is_numeric();
-            if on_part && is_number {
-                continue;
-            }
-            if on_part && !is_number {
-                let part = Part::new(line[starting_idx as usize..current_x as usize].parse().unwrap(), Location { x: starting_idx, y: current_y }, (current_x - starting_idx) as usize);
-                parts.push(part);
-                part_locations.entry(current_y).or_default().push(part);
-                on_part = false;
-            }
-            if !is_number {
-                if c == '.' {
-                    continue;
-                }
-                let symbol = Symbol {
-                    symbol: c,
-                    location: Location { x: current_x, y: current_y },
-                };
-                symbol_locations.entry(current_y).or_default().push(symbol);
-                symbols.push(symbol);
-                continue;
-            }
-
-            if !on_part && is_number {
-                on_part = true;
-                starting_idx = current_x;
-            }
+    let mut occupancy = Grid::filled(grid.width(), grid.height(), None);
+    for run in grid.runs(|c| is_digit(c)) {
+        let digits = run.cells(&grid).concat();
+        for location in run.positions() {
+            occupancy[location] = Some(parts.len());
         }
-        if on_part {
-            let part = Part::new(line[starting_idx as usize..].parse().unwrap(), Location { x: starting_idx, y: current_y }, (line.len() as isize - starting_idx) as usize);
-            part_locations.entry(current_y).or_default().push(part);
-            parts.push(part);
+        parts.push(Part::new(parse_part_id(&digits, run.start.row)?, run.start, run.len));
+    }
+    for (location, &c) in grid.iter() {
+        if is_digit(c) || c == "." {
+            continue;
         }
+        symbols.push(Symbol { symbol: c.to_string(), location });
     }
-    Schema {
+    Ok(Schema {
         parts,
         symbols,
-        symbol_locations,
-        part_locations,
-    }
+        occupancy,
+    })
+}
+
+/// Checks for a cell holding a single ASCII digit, without marks over it.
+fn is_digit(cell: &str) -> bool {
+    cell.len() == 1 && cell.as_bytes()[0].is_ascii_digit()
+}
+
+fn parse_part_id(digits: &str, row: usize) -> Result<usize, SolveError> {
+    digits.parse().map_err(|err| {
+        SolveError::new(format!("invalid part number {digits:?}: {err}"))
+            .with_line(row)
+            .with_context("parsing schematic")
+    })
 }
 
 impl solver::Solver for Day3Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String {
-        // We capture the symbols with a HashMap<row, Vec<symbol>>. The symbols are ordered left to right.
-        // As soon as we find one symbol that is adjacent to a part or a symbol that is too far to the
-        // right, we can stop looking for that symbol.
-        // We create a vector of all the parts.
-        let schema = schema_from_lines(lines);
-        let mut sum_of_actual_parts = 0;
-        for part in schema.parts {
-            let part_row = part.location.y;
-            let has_adjacent_symbols = (part_row - 1..=part_row + 1)
-                .flat_map(|row| schema.symbol_locations.get(&row).map_or(Vec::new(), |symbols| symbols.clone()))
-                .any(|symbol| part.is_adjacent_to(&symbol.location));
-
-            if has_adjacent_symbols {
-                sum_of_actual_parts += part.id;
-            }
+    type Input = Schema;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        schema_from_lines(input)
+    }
+
+    fn solve_part_1(&self, schema: &Self::Input) -> Result<Answer, SolveError> {
+        self.solve_explained(schema, solver::Part::One, &mut Discard)
+    }
+
+    fn solve_part_2(&self, schema: &Self::Input) -> Result<Answer, SolveError> {
+        self.solve_explained(schema, solver::Part::Two, &mut Discard)
+    }
+
+    fn solve_explained(&self, schema: &Self::Input, part: solver::Part, events: &mut dyn EventSink) -> Result<Answer, SolveError> {
+        match part {
+            solver::Part::One => Ok(sum_of_part_numbers(schema, events).into()),
+            solver::Part::Two => Ok(sum_of_gear_ratios(schema, events).into()),
         }
-        sum_of_actual_parts.to_string()
     }
+}
 
-    fn solve_part_2(&self, lines: Vec<String>) -> String {
-        let schema = schema_from_lines(lines);
-        let mut sum_gear_ratios = 0usize;
-        for symbol in schema.symbols.iter() {
-            // Skip if it's not a gear
-            if symbol.symbol != '*' {
-                continue;
-            }
+/// Adds up the numbers of the parts next to a symbol. How many times each symbol is drawn is
+/// reported first, then every part with its position, 0-based, its width and the symbol that makes
+/// it count, if any, and last the parts that don't count.
+fn sum_of_part_numbers(schema: &Schema, events: &mut dyn EventSink) -> usize {
+    if events.enabled() {
+        for (symbol, count) in schema.symbol_histogram() {
+            events.record(Event::new("symbol").with("class", symbol).with("count", count));
+        }
+    }
+    let mut sum_of_actual_parts = 0;
+    for (part, adjacent_symbol) in schema.parts.iter().zip(schema.adjacent_symbols()) {
+        if events.enabled() {
+            let event = Event::new("part")
+                .with("id", part.id)
+                .with("row", part.location.row)
+                .with("column", part.location.col)
+                .with("width", part.width);
+            events.record(match adjacent_symbol {
+                Some(symbol) => event.with("symbol", symbol),
+                None => event.with("symbol", "none"),
+            });
+        }
+        if adjacent_symbol.is_some() {
+            sum_of_actual_parts += part.id;
+        }
+    }
+    if events.enabled() {
+        let ids = schema.parts_without_symbols().map(|part| part.id.to_string()).collect::<Vec<_>>();
+        events.record(Event::new("unattached").with("parts", ids.join(",")));
+    }
+    sum_of_actual_parts
+}
 
-            let adjacent_parts = schema.parts_adjacent_to_point(&symbol.location);
-            // Too many adjacent parts to be a gear ratio
-            if adjacent_parts.len() != 2 {
-                continue;
+/// Adds up the ratios of the gears, the `*` next to exactly two parts. Every gear is reported with
+/// its position, 0-based, its parts and its ratio, followed by the `*` that are next to parts but
+/// aren't gears.
+fn sum_of_gear_ratios(schema: &Schema, events: &mut dyn EventSink) -> usize {
+    let mut sum_gear_ratios = 0usize;
+    for (gear, parts) in schema.symbols_next_to_parts("*", Adjacency::Exactly(2)) {
+        let ratio = parts.clone().map(|part| schema.parts[part].id).product::<usize>();
+        if events.enabled() {
+            let ids = parts.map(|part| schema.parts[part].id.to_string()).collect::<Vec<_>>();
+            events.record(
+                Event::new("gear")
+                    .with("row", gear.location.row)
+                    .with("column", gear.location.col)
+                    .with("parts", ids.join(","))
+                    .with("ratio", ratio),
+            );
+        }
+        sum_gear_ratios += ratio;
+    }
+    if events.enabled() {
+        for (star, parts) in schema.symbols_next_to_parts("*", Adjacency::AtLeast(1)) {
+            let ids = parts.map(|part| schema.parts[part].id.to_string()).collect::<Vec<_>>();
+            if ids.len() != 2 {
+                events.record(
+                    Event::new("star")
+                        .with("row", star.location.row)
+                        .with("column", star.location.col)
+                        .with("parts", ids.join(",")),
+                );
             }
-            sum_gear_ratios += adjacent_parts[0].id * adjacent_parts[1].id;
         }
-        sum_gear_ratios.to_string()
     }
+    sum_gear_ratios
 }
 
 #[derive(Debug, Copy, Clone)]
 struct Part {
     id: usize,
-    location: Location,
+    location: Pos,
     width: usize,
 }
 
 impl Part {
-    fn new(id: usize, location: Location, width: usize) -> Part {
+    fn new(id: usize, location: Pos, width: usize) -> Part {
         Part {
             id,
             location,
@@ -141,90 +257,216 @@ impl Part {
         }
     }
 
-    fn is_adjacent_to(&self, location: &Location) -> bool {
+    /// Checks the bounding box of the part, to test the occupancy index against.
+    #[cfg(test)]
+    fn is_adjacent_to(&self, location: Pos) -> bool {
         // The part can be represented as a 1 x width segment. Create a bounding box of
         // 3 x width + 2, and check if the symbol is within that box. In the diagram below
         // the segment is made with • and the bounding box is made with +.
         // +++++++
         // +•••••+
         // +++++++
-        let min_x = self.location.x - 1;
-        // Looks stupid, but I want to capture that the end of the segment is
-        // self.location.0 + self.width - 1, and then we move to the right by one.
-        let max_x = (self.location.x + self.width as isize - 1) + 1;
-        let min_y = self.location.y - 1;
-        let max_y = self.location.y + 1;
-        //println!("At part {:?}", self);
-        //println!("Does symbol {:?} fit in bounding box ({}, {}, {}, {})?", symbol, min_x, max_x, min_y, max_y);
-        min_x <= location.x && location.x <= max_x && min_y <= location.y && location.y <= max_y
+        // The box can stick out of the top or the left of the grid, so the location is moved by
+        // one instead of the box.
+        let (row, col) = (location.row + 1, location.col + 1);
+        let min_col = self.location.col;
+        let max_col = self.location.col + self.width + 1;
+        let min_row = self.location.row;
+        let max_row = self.location.row + 2;
+        min_col <= col && col <= max_col && min_row <= row && row <= max_row
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 struct Symbol {
-    symbol: char,
-    location: Location,
-}
-
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct Location {
-    x: isize,
-    y: isize,
+    symbol: String,
+    location: Pos,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::y2023::day03::Day3Solver;
-    use crate::utils::lines::lines_from_file;
+    use crate::y2023::day03::{schema_from_lines, Adjacency, Day3Solver};
+    use crate::utils::grid::Pos;
+    use crate::solver::Part;
+    use crate::test_support::{example, require_input, Rng};
     use crate::solver::Solver;
 
+    #[test]
+    fn test_schema_from_lines() {
+        let schema = schema_from_lines("467..114..\n...*......\n..35..633.").unwrap();
+        let ids = schema.parts.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![467, 114, 35, 633]);
+        assert_eq!(schema.symbols.len(), 1);
+        assert_eq!(schema.symbols[0].location, Pos::new(1, 3));
+        let adjacent = schema.parts_adjacent_to_point(schema.symbols[0].location);
+        assert_eq!(adjacent.map(|idx| schema.parts[idx].id).collect::<Vec<_>>(), vec![467, 35]);
+    }
+
+    #[test]
+    fn test_ragged_rows() {
+        let schema = schema_from_lines("467..114\n...*\n..35..633.").unwrap();
+        let ids = schema.parts.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![467, 114, 35, 633]);
+        let solver = Day3Solver {};
+        assert_eq!(solver.solve_part_1(&schema).unwrap(), "502");
+        assert_eq!(solver.solve_part_2(&schema).unwrap(), "16345");
+    }
+
+    #[test]
+    fn test_non_ascii_symbols() {
+        // Each symbol takes a single column whatever its length in bytes.
+        let schema = schema_from_lines("é12.\n..€.\n34.£").unwrap();
+        let symbols = schema.symbols.iter().map(|s| (s.symbol.as_str(), s.location)).collect::<Vec<_>>();
+        assert_eq!(symbols, vec![("é", Pos::new(0, 0)), ("€", Pos::new(1, 2)), ("£", Pos::new(2, 3))]);
+        let parts = schema.parts.iter().map(|p| (p.id, p.location, p.width)).collect::<Vec<_>>();
+        assert_eq!(parts, vec![(12, Pos::new(0, 1), 2), (34, Pos::new(2, 0), 2)]);
+        let around_euro = schema.parts_adjacent_to_point(Pos::new(1, 2)).collect::<Vec<_>>();
+        assert_eq!(around_euro, vec![0, 1]);
+
+        let solver = Day3Solver {};
+        let input = solver.parse("1€\n.2").unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "3");
+        assert_eq!(solver.solve_part_1(&solver.parse("..中\n.7.").unwrap()).unwrap(), "7");
+
+        // e + U+0301 is drawn as é and is a single symbol in a single column, so the accent
+        // doesn't reach any further than the e.
+        let schema = schema_from_lines("e\u{301}..\n..5.").unwrap();
+        let symbols = schema.symbols.iter().map(|s| (s.symbol.as_str(), s.location)).collect::<Vec<_>>();
+        assert_eq!(symbols, vec![("e\u{301}", Pos::new(0, 0))]);
+        assert_eq!(schema.parts[0].location, Pos::new(1, 2));
+        assert_eq!(solver.solve_part_1(&schema).unwrap(), "0");
+        let schema = schema_from_lines(".e\u{301}\n..5").unwrap();
+        assert_eq!(solver.solve_part_1(&schema).unwrap(), "5");
+    }
+
+    #[test]
+    fn test_non_ascii_digits() {
+        // Arabic-Indic and fullwidth digits count as numeric but aren't part numbers.
+        let err = schema_from_lines("467..\n..٣5*").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: parsing schematic: \"٣\" in column 3 is not an ASCII digit");
+        let err = schema_from_lines("１2*..").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: parsing schematic: \"１\" in column 1 is not an ASCII digit");
+        // So is anything else that reads as a number, and a digit with a mark over it.
+        assert!(schema_from_lines("½1").is_err());
+        let err = schema_from_lines("1.\n.3\u{301}").unwrap_err();
+        assert_eq!(err.line, Some(2));
+    }
+
+    /// Draws a schematic with runs of digits of any length and a few symbols.
+    fn random_schematic(rng: &mut Rng, width: usize, height: usize) -> String {
+        let rows = (0..height).map(|_| {
+            (0..width)
+                .map(|_| match rng.below(10) {
+                    0..=3 => '.',
+                    4 => '*',
+                    5 => '#',
+                    _ => char::from(b'1' + rng.below(9) as u8),
+                })
+                .collect::<String>()
+        });
+        rows.collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_occupancy_matches_bounding_boxes() {
+        let mut rng = Rng::new(3);
+        for _ in 0..200 {
+            let schema = schema_from_lines(&random_schematic(&mut rng, 8, 6)).unwrap();
+            for row in 0..6 {
+                for col in 0..8 {
+                    let location = Pos::new(row, col);
+                    let expected = (0..schema.parts.len())
+                        .filter(|&idx| schema.parts[idx].is_adjacent_to(location))
+                        .collect::<Vec<_>>();
+                    assert_eq!(schema.parts_adjacent_to_point(location).collect::<Vec<_>>(), expected, "{location}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_part_events() {
+        let solver = Day3Solver {};
+        let input = solver.parse("467..114..\n...*......\n..35..633.").unwrap();
+        let mut events = vec![];
+        assert_eq!(solver.solve_explained(&input, Part::One, &mut events).unwrap(), "502");
+        let lines = events.iter().map(|event| event.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "symbol class=* count=1",
+                "part id=467 row=0 column=0 width=3 symbol=*",
+                "part id=114 row=0 column=5 width=3 symbol=none",
+                "part id=35 row=2 column=2 width=2 symbol=*",
+                "part id=633 row=2 column=6 width=3 symbol=none",
+                "unattached parts=114,633",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gear_events() {
+        let solver = Day3Solver {};
+        let input = solver.parse(&example(2023, 3, Part::Two)).unwrap();
+        let mut events = vec![];
+        assert_eq!(solver.solve_explained(&input, Part::Two, &mut events).unwrap(), "467835");
+        let lines = events.iter().map(|event| event.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "gear row=1 column=3 parts=467,35 ratio=16345",
+                "gear row=8 column=5 parts=755,598 ratio=451490",
+                "star row=4 column=3 parts=617",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_queries() {
+        let schema = schema_from_lines(&example(2023, 3, Part::One)).unwrap();
+        let ids = |parts: &mut dyn Iterator<Item=usize>| parts.map(|idx| schema.parts[idx].id).collect::<Vec<_>>();
+        let mut stars = schema
+            .symbols_next_to_parts("*", Adjacency::AtLeast(1))
+            .map(|(symbol, mut parts)| (symbol.location, ids(&mut parts)));
+        assert_eq!(stars.next(), Some((Pos::new(1, 3), vec![467, 35])));
+        assert_eq!(stars.next(), Some((Pos::new(4, 3), vec![617])));
+        assert_eq!(stars.next(), Some((Pos::new(8, 5), vec![755, 598])));
+        assert_eq!(stars.next(), None);
+        assert_eq!(schema.symbols_next_to_parts("*", Adjacency::Exactly(1)).count(), 1);
+        assert_eq!(schema.symbols_next_to_parts("#", Adjacency::AtLeast(1)).count(), 1);
+        assert_eq!(schema.symbols_next_to_parts("*", Adjacency::AtLeast(3)).count(), 0);
+
+        let lonely = schema.parts_without_symbols().map(|part| part.id).collect::<Vec<_>>();
+        assert_eq!(lonely, vec![114, 58]);
+        let histogram = schema.symbol_histogram().into_iter().collect::<Vec<_>>();
+        assert_eq!(histogram, vec![("#", 1), ("$", 1), ("*", 3), ("+", 1)]);
+    }
+
     #[test]
     fn test_part_1_unit() {
         let solver = Day3Solver {};
-        let lines = vec![
-            "467..114..".to_string(),
-            "...*......".to_string(),
-            "..35..633.".to_string(),
-            "......#...".to_string(),
-            "617*......".to_string(),
-            ".....+.58.".to_string(),
-            "..592.....".to_string(),
-            "......755.".to_string(),
-            "...$.*....".to_string(),
-            ".664.598..".to_string(),
-        ];
-        assert_eq!(solver.solve_part_1(lines), "4361");
+        let input = solver.parse(&example(2023, 3, Part::One)).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "4361");
     }
 
     #[test]
     fn test_part_2_unit() {
         let solver = Day3Solver {};
-        let lines = vec![
-            "467..114..".to_string(),
-            "...*......".to_string(),
-            "..35..633.".to_string(),
-            "......#...".to_string(),
-            "617*......".to_string(),
-            ".....+.58.".to_string(),
-            "..592.....".to_string(),
-            "......755.".to_string(),
-            "...$.*....".to_string(),
-            ".664.598..".to_string(),
-        ];
-        assert_eq!(solver.solve_part_2(lines), "467835");
+        let input = solver.parse(&example(2023, 3, Part::Two)).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "467835");
     }
 
     #[test]
     fn test_part_1() {
         let solver = Day3Solver {};
-        let lines = lines_from_file("./inputs/day03.txt");
-        assert_eq!(solver.solve_part_1(lines), "550064");
+        let input = solver.parse(&require_input!(2023, 3)).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "550064");
     }
 
     #[test]
     fn test_part_2() {
         let solver = Day3Solver {};
-        let lines = lines_from_file("./inputs/day03.txt");
-        assert_eq!(solver.solve_part_2(lines), "85010461");
+        let input = solver.parse(&require_input!(2023, 3)).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "85010461");
     }
 }
\ No newline at end of file
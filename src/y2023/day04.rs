@@ -5,41 +5,69 @@ use nom::combinator::map_res;
 use nom::IResult;
 use nom::multi::separated_list1;
 use crate::solver;
+use crate::solver::{Answer, SolveError};
+use crate::utils::parsers::parse_line;
+use crate::registry::SolverRegistry;
 
 pub struct Day4Solver {}
 
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 4, Box::new(Day4Solver {}));
+}
+
 impl solver::Solver for Day4Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String {
+    type Input = Vec<Card>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| parse_line(idx, line, "parsing card", parse_card))
+            .collect()
+    }
+
+    fn solve_part_1(&self, cards: &Self::Input) -> Result<Answer, SolveError> {
         let mut points_won = 0;
-        for line in lines {
-            let (_, card) = parse_card(&line).unwrap();
+        for card in cards {
             let points = card.points();
             points_won += points;
         }
-        points_won.to_string()
+        Ok(points_won.into())
     }
 
-    fn solve_part_2(&self, lines: Vec<String>) -> String {
+    fn solve_part_2(&self, cards: &Self::Input) -> Result<Answer, SolveError> {
         // Each index represents the number of cards we have so far for each
-        let mut cards_to_process = vec![1; lines.len()];
-        for (idx, line) in lines.iter().enumerate() {
+        let mut cards_to_process = vec![1; cards.len()];
+        for (idx, card) in cards.iter().enumerate() {
+            // Copies are won by card number, so the numbers have to follow the table
+            if card.number != idx + 1 {
+                return Err(SolveError::new(format!("card {} is at position {}", card.number, idx + 1))
+                    .with_line(idx));
+            }
             // By the time we've gotten to this card we have a certain number of cards we need to resolve
             let num_copies_of_card = cards_to_process[idx];
-            let (_, card) = parse_card(line).unwrap();
             let points = card.num_matches();
+            if idx + points >= cards.len() {
+                return Err(SolveError::new(format!(
+                    "card {} wins copies of {points} cards but only {} follow it",
+                    card.number,
+                    cards.len() - idx - 1,
+                )).with_line(idx));
+            }
             for i in 0..points {
                 // We get a copy for each match, but we have to multiply it by the number of copies
                 // we had by the time we got to this card
                 cards_to_process[card.number + i] += num_copies_of_card;
             }
         }
-        cards_to_process.iter().sum::<isize>().to_string()
+        Ok(cards_to_process.iter().sum::<isize>().into())
     }
 }
 
 // A Card has a number, a HashSet of winning numbers and a
 // HashSet of numbers that are present
-struct Card {
+#[derive(Debug)]
+pub struct Card {
     number: usize,
     winning_numbers: HashSet<usize>,
     numbers: HashSet<usize>,
@@ -90,52 +118,62 @@ fn parse_card(line: &str) -> IResult<&str, Card> {
 #[cfg(test)]
 mod tests {
     use crate::solver::Solver;
-    use crate::utils::lines::lines_from_file;
+    use crate::solver::Part;
+    use crate::test_support::{example, require_input};
     use crate::y2023::day04::Day4Solver;
 
     #[test]
     fn test_part_1_unit_test() {
         let solver = Day4Solver {};
-        let lines = [
-            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
-            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
-            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
-            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
-            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
-            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
-        ].iter().map(|s| s.to_string()).collect();
-        let result = solver.solve_part_1(lines);
-        assert_eq!(result, "13");
+        let input = solver.parse(&example(2023, 4, Part::One)).unwrap();
+        let result = solver.solve_part_1(&input);
+        assert_eq!(result.unwrap(), "13");
     }
 
     #[test]
     fn test_part_1() {
         let solver = Day4Solver {};
-        let lines = lines_from_file("inputs/day04.txt");
-        let result = solver.solve_part_1(lines);
-        assert_eq!(result, "18519");
+        let input = solver.parse(&require_input!(2023, 4)).unwrap();
+        let result = solver.solve_part_1(&input);
+        assert_eq!(result.unwrap(), "18519");
     }
 
     #[test]
     fn test_part_2_unit_test() {
         let solver = Day4Solver {};
-        let lines = [
+        let input = solver.parse(&example(2023, 4, Part::Two)).unwrap();
+        let result = solver.solve_part_2(&input);
+        assert_eq!(result.unwrap(), "30");
+    }
+
+    #[test]
+    fn test_malformed_card() {
+        let solver = Day4Solver {};
+        let err = solver.parse(&[
             "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
-            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
-            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
-            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
-            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
-            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
-        ].iter().map(|s| s.to_string()).collect();
-        let result = solver.solve_part_2(lines);
-        assert_eq!(result, "30");
+            "Card 2: 13 32 20 16 61 - 61 30 68 82 17 32 24 19",
+        ].join("\n")).unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.context.as_deref(), Some("parsing card"));
+    }
+
+    #[test]
+    fn test_part_2_malformed_table() {
+        let solver = Day4Solver {};
+        let input = solver.parse("Card 1: 1 | 1").unwrap();
+        let err = solver.solve_part_2(&input).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: card 1 wins copies of 1 cards but only 0 follow it");
+
+        let input = solver.parse(&["Card 1: 1 | 2", "Card 3: 1 | 2"].join("\n")).unwrap();
+        let err = solver.solve_part_2(&input).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: card 3 is at position 2");
     }
 
     #[test]
     fn test_part_2() {
         let solver = Day4Solver {};
-        let lines = lines_from_file("inputs/day04.txt");
-        let result = solver.solve_part_2(lines);
-        assert_eq!(result, "11787590");
+        let input = solver.parse(&require_input!(2023, 4)).unwrap();
+        let result = solver.solve_part_2(&input);
+        assert_eq!(result.unwrap(), "11787590");
     }
 }
@@ -1,299 +1,573 @@
-use crate::solver::Solver;
-use crate::utils::parsers::parse_number;
-use nom::character::complete::space1;
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::solver::{Answer, Discard, Event, EventSink, Part, Solver, SolveError};
+use crate::utils::interval::{Interval, IntervalSet, PiecewiseShift};
+use crate::utils::parsers::{parse_line, parse_number};
+use nom::bytes::complete::tag;
+use nom::character::complete::{alpha1, space1};
 use nom::IResult;
+use nom::multi::separated_list1;
+use crate::registry::SolverRegistry;
 
 pub struct Day5Solver {}
 
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 5, Box::new(Day5Solver {}));
+}
+
 impl Solver for Day5Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String {
-        let seeds = lines[0]
-            .split("seeds: ")
-            .collect::<Vec<_>>()
-            .get(1)
-            .unwrap()
-            .split(' ')
-            .collect::<Vec<_>>();
-        let lines = &lines[2..];
-        let mapper_info: Vec<&[String]> = lines.split(|line| line.is_empty()).collect::<Vec<_>>();
-        let mut mappers = vec![];
-        for mapper in mapper_info.iter() {
-            let mut maps = vec![];
-            for (idx, line) in mapper.iter().enumerate() {
-                if idx == 0 {
-                    continue;
+    type Input = Almanac;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        let lines = input.lines().collect::<Vec<_>>();
+        let first_line = lines.first().copied().unwrap_or_default();
+        let seeds = parse_line(0, first_line, "parsing seeds", parse_seeds)?;
+        let mut maps: BTreeMap<String, CategoryMap> = BTreeMap::new();
+        // Keep track of the line index so that errors point at the right line. The map blocks
+        // start after the seeds line and the blank line that follows it.
+        let mut line_idx = 2;
+        for block in lines.get(2..).unwrap_or_default().split(|line| line.is_empty()) {
+            let Some(header) = block.first() else {
+                line_idx += 1;
+                continue;
+            };
+            let (from, to) = parse_line(line_idx, header, "parsing map header", parse_map_header)?;
+            if let Some(existing) = maps.get(from) {
+                return Err(SolveError::new(format!(
+                    "there is already a map from {from} on line {}",
+                    existing.line_idx + 1
+                ))
+                .with_line(line_idx)
+                .with_context("parsing map header"));
+            }
+            let mut pieces = vec![];
+            for (idx, line) in block.iter().enumerate().skip(1) {
+                let map_line = parse_line(line_idx + idx, line, "parsing map range", parse_range_mapper)?;
+                let piece = map_line
+                    .to_piece()
+                    .map_err(|err| err.with_line(line_idx + idx).with_context("parsing map range"))?;
+                pieces.push((piece, line_idx + idx));
+            }
+            // A value can only be converted one way, so the source ranges can't overlap.
+            pieces.sort_by_key(|((source, _), _)| *source);
+            for pair in pieces.windows(2) {
+                let (((first, _), first_idx), ((second, _), second_idx)) = (pair[0], pair[1]);
+                if first.overlaps(&second) {
+                    // Point at the later of the two lines.
+                    let ((earlier, earlier_idx), (later, later_idx)) = if first_idx < second_idx {
+                        ((first, first_idx), (second, second_idx))
+                    } else {
+                        ((second, second_idx), (first, first_idx))
+                    };
+                    return Err(SolveError::new(format!(
+                        "source range {later} overlaps {earlier} on line {}",
+                        earlier_idx + 1
+                    ))
+                    .with_line(later_idx)
+                    .with_context("parsing map range"));
                 }
-                let (_, map) = parse_range_mapper(line).unwrap();
-                maps.push(map);
             }
-            // We've created all the number ranges. Collect them into a MapRangeLayer.
-            mappers.push(MapRangeLayer::from_ranges(maps));
+            let function = PiecewiseShift::new(pieces.into_iter().map(|(piece, _)| piece))
+                .expect("source ranges were checked for overlaps");
+            maps.insert(from.to_string(), CategoryMap { to: to.to_string(), function, line_idx });
+            line_idx += block.len() + 1;
         }
-        let evaluator = MapRangeCombiner { mappers };
+        // The puzzle asks about locations, so the maps have to lead from seed to location.
+        let evaluator = MapRangeCombiner::new(&category_path(&maps, "seed", "location")?);
+        Ok(Almanac { seeds, maps, evaluator })
+    }
 
-        let mut min_seed_value = usize::MAX;
+    fn solve_part_1(&self, almanac: &Self::Input) -> Result<Answer, SolveError> {
+        self.solve_explained(almanac, Part::One, &mut Discard)
+    }
 
-        for seed in seeds {
-            // Create ranges that have a single element and find the min out of all of them.
-            let val = seed.parse::<usize>().unwrap();
-            let values = evaluator.evaluate_range(&Range::new(val, val), 0);
-            for val in values {
-                if val.start < min_seed_value {
-                    min_seed_value = val.start;
-                }
+    fn solve_part_2(&self, almanac: &Self::Input) -> Result<Answer, SolveError> {
+        if almanac.seeds.len() % 2 != 0 {
+            return Err(SolveError::new("seeds must come in (start, length) pairs")
+                .with_line(0)
+                .with_context("parsing seeds"));
+        }
+
+        let mut seed_ranges = IntervalSet::new();
+        for chunk in almanac.seeds.chunks(2) {
+            let (seed_start, seed_range) = (chunk[0], chunk[1]);
+            // A range of length zero holds no seeds.
+            if seed_range == 0 {
+                continue;
             }
+            let range = Interval::with_len(seed_start, seed_range as u128).ok_or_else(|| {
+                SolveError::new(format!("seed range {seed_start} {seed_range} ends past {}", usize::MAX))
+                    .with_line(0)
+                    .with_context("parsing seeds")
+            })?;
+            seed_ranges.insert(range);
         }
-        min_seed_value.to_string()
+
+        // The seed ranges cover billions of seeds, but the locations can be walked one range of
+        // the composed map at a time, from the lowest up, until one of them was planted.
+        Ok(almanac.evaluator.min_location(&seed_ranges, LocationSearch::Inverse)?.into())
     }
 
-    fn solve_part_2(&self, lines: Vec<String>) -> String {
-        let seeds = lines[0]
-            .split("seeds: ")
-            .collect::<Vec<_>>()
-            .get(1)
-            .unwrap()
-            .split(' ')
-            .collect::<Vec<_>>();
-        let lines = &lines[2..];
-        let mapper_info: Vec<&[String]> = lines.split(|line| line.is_empty()).collect::<Vec<_>>();
-        let mut mappers = vec![];
-        for mapper in mapper_info.iter() {
-            let mut maps = vec![];
-            for (idx, line) in mapper.iter().enumerate() {
-                if idx == 0 {
-                    continue;
+    fn solve_explained(&self, almanac: &Self::Input, part: Part, events: &mut dyn EventSink) -> Result<Answer, SolveError> {
+        match part {
+            Part::One => {
+                if events.enabled() {
+                    almanac.walk_seeds(events)?;
                 }
-                let (_, map) = parse_range_mapper(line).unwrap();
-                maps.push(map);
+                // Each seed is a range that holds a single element.
+                let seeds = almanac.seeds.iter().map(|&seed| Interval::point(seed)).collect();
+                Ok(almanac.evaluator.min_location(&seeds, LocationSearch::Forward)?.into())
             }
-            // We've created all the number ranges. Collect them into a MapRangeLayer.
-            mappers.push(MapRangeLayer::from_ranges(maps));
+            Part::Two => self.solve_part_2(almanac),
         }
-        let evaluator = MapRangeCombiner { mappers };
-
-        let seed_ranges = seeds
-            .chunks(2)
-            .map(|chunk| {
-                let seed_start = chunk[0].parse::<usize>().unwrap();
-                let seed_range = chunk[1].parse::<usize>().unwrap();
-                Range::new(seed_start, seed_start + seed_range - 1)
-            })
-            .collect::<Vec<Range>>();
-
-        find_min_location_for_seed_range(seed_ranges, &evaluator).to_string()
     }
 }
 
-fn find_min_location_for_seed_range(
-    seed_ranges: Vec<Range>,
-    evaluator: &MapRangeCombiner,
-) -> usize {
-    let mut min_seed_value = usize::MAX;
-    for seed_range in seed_ranges {
-        for val in evaluator.evaluate_range(&seed_range, 0) {
-            if val.start < min_seed_value {
-                min_seed_value = val.start;
-            }
+/// Almanac is the parsed puzzle input: the seeds line, the map out of every category and the
+/// chain of those maps that takes a seed all the way to a location.
+#[derive(Debug)]
+pub struct Almanac {
+    seeds: Vec<usize>,
+    /// Keyed by the category each map converts from.
+    maps: BTreeMap<String, CategoryMap>,
+    evaluator: MapRangeCombiner,
+}
+
+impl Almanac {
+    /// Returns the combined map from one category to another, e.g. from seed to humidity.
+    fn combiner(&self, from: &str, to: &str) -> Result<MapRangeCombiner, SolveError> {
+        Ok(MapRangeCombiner::new(&category_path(&self.maps, from, to)?))
+    }
+
+    /// Reports every seed with the value it has in each category on its way to a location, like
+    /// the puzzle's walkthrough does.
+    fn walk_seeds(&self, events: &mut dyn EventSink) -> Result<(), SolveError> {
+        let categories = category_path(&self.maps, "seed", "location")?
+            .into_iter()
+            .map(|(_, map)| Ok((map.to.as_str(), self.combiner("seed", &map.to)?)))
+            .collect::<Result<Vec<_>, SolveError>>()?;
+        for &seed in self.seeds.iter() {
+            let event = categories
+                .iter()
+                .fold(Event::new("seed").with("number", seed), |event, (category, combiner)| {
+                    event.with(*category, combiner.apply(seed))
+                });
+            events.record(event);
+        }
+        Ok(())
+    }
+}
+
+/// CategoryMap is one `X-to-Y map:` block, the map that converts category X to category Y.
+#[derive(Debug)]
+struct CategoryMap {
+    to: String,
+    function: PiecewiseShift<usize>,
+    /// The line of the block's header.
+    line_idx: usize,
+}
+
+/// Returns the names and maps that lead from one category to another, in order. Every category
+/// has at most one map out of it, so the maps form chains and there is at most one way to go.
+fn category_path<'a>(
+    maps: &'a BTreeMap<String, CategoryMap>,
+    from: &'a str,
+    to: &str,
+) -> Result<Vec<(&'a str, &'a CategoryMap)>, SolveError> {
+    let mut path = vec![];
+    let mut category = from;
+    while category != to {
+        let Some(map) = maps.get(category) else {
+            return Err(SolveError::new(format!("no map from {category} on the way from {from} to {to}")));
+        };
+        // A chain that comes back to a category it went through will never get anywhere new.
+        if path.iter().any(|(visited, _)| *visited == category) {
+            return Err(SolveError::new(format!("maps from {from} loop back to {category} without reaching {to}")));
         }
+        path.push((category, map));
+        category = &map.to;
     }
-    min_seed_value
+    Ok(path)
+}
+
+fn parse_map_header(line: &str) -> IResult<&str, (&str, &str)> {
+    let (line, from) = alpha1(line)?;
+    let (line, _) = tag("-to-")(line)?;
+    let (line, to) = alpha1(line)?;
+    let (line, _) = tag(" map:")(line)?;
+    Ok((line, (from, to)))
+}
+
+fn parse_seeds(line: &str) -> IResult<&str, Vec<usize>> {
+    let (line, _) = tag("seeds:")(line)?;
+    let (line, _) = space1(line)?;
+    separated_list1(space1, parse_number)(line)
 }
 
 #[cfg(test)]
 mod tests_mind {
-    use crate::utils::lines::lines_from_file;
+    use crate::solver::Part;
+    use crate::test_support::{example, require_input, Rng};
     use crate::y2023::day05::*;
 
+    /// Chains the functions as maps between made up categories.
+    fn chain(functions: Vec<PiecewiseShift<usize>>) -> MapRangeCombiner {
+        let maps = functions
+            .into_iter()
+            .enumerate()
+            .map(|(idx, function)| (idx.to_string(), CategoryMap { to: (idx + 1).to_string(), function, line_idx: 0 }))
+            .collect::<BTreeMap<_, _>>();
+        MapRangeCombiner::new(&category_path(&maps, "0", &maps.len().to_string()).unwrap())
+    }
+
     #[test]
-    fn test_seed_ranges_min_location() {
-        let seed_ranges = vec![Range::new(1, 10)];
-        let map_range_combiner = MapRangeCombiner {
-            mappers: vec![
-                MapRangeLayer::from_ranges(vec![MapRange::new(1, 5, 5), MapRange::new(6, 10, -4)]),
-                MapRangeLayer::from_ranges(vec![
-                    MapRange::new(1, 3, 7),
-                    MapRange::new(4, 6, -3),
-                    MapRange::new(7, 10, -3),
-                ]),
-            ],
-        };
+    fn test_parse_almanac() {
+        let almanac = Day5Solver {}.parse(&example(2023, 5, Part::One)).unwrap();
+        assert_eq!(almanac.seeds, vec![79, 14, 55, 13]);
+        // Seed 79 goes through all seven maps to location 82 in the puzzle's walkthrough.
         assert_eq!(
-            find_min_location_for_seed_range(seed_ranges, &map_range_combiner),
-            1
+            almanac.evaluator.evaluate(&Interval::point(79).into()),
+            Interval::point(82).into()
         );
     }
 
     #[test]
-    fn test_part_1() {
+    fn test_example() {
         let solver = Day5Solver {};
-        let lines = lines_from_file("inputs/2023-day05.txt");
-        let result = solver.solve_part_1(lines);
-        assert_eq!(result, "551761867");
+        let almanac = solver.parse(&example(2023, 5, Part::One)).unwrap();
+        assert_eq!(solver.solve_part_1(&almanac).unwrap(), "35");
+        assert_eq!(solver.solve_part_2(&almanac).unwrap(), "46");
     }
 
     #[test]
-    fn test_part_2() {
-        let solver = Day5Solver {};
-        let lines = lines_from_file("inputs/2023-day05.txt");
-        let result = solver.solve_part_2(lines);
-        assert_eq!(result, "57451709");
+    fn test_seed_ranges_min_location() {
+        let seed_ranges = Interval::new(1, 10).into();
+        let map_range_combiner = chain(vec![
+            PiecewiseShift::new(vec![(Interval::new(1, 5), 5), (Interval::new(6, 10), -4)]).unwrap(),
+            PiecewiseShift::new(vec![
+                (Interval::new(1, 3), 7),
+                (Interval::new(4, 6), -3),
+                (Interval::new(7, 10), -3),
+            ])
+            .unwrap(),
+        ]);
+        assert_eq!(map_range_combiner.min_location(&seed_ranges, LocationSearch::Forward).unwrap(), 1);
+        assert_eq!(map_range_combiner.min_location(&seed_ranges, LocationSearch::Inverse).unwrap(), 1);
     }
-}
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct Range {
-    // Both start and end are inclusive
-    start: usize,
-    end: usize,
-}
+    #[test]
+    fn test_composed_map() {
+        let almanac = Day5Solver {}.parse(&example(2023, 5, Part::One)).unwrap();
+        let composed = &almanac.evaluator.forward;
+        // The seeds and locations from the puzzle's walkthrough.
+        for (seed, location) in [(79, 82), (14, 43), (55, 86), (13, 35)] {
+            assert_eq!(composed.apply(seed), Some(location), "seed {seed}");
+        }
+        assert_eq!(almanac.evaluator.to_string().lines().next(), Some("[0, 13] +22"));
+    }
 
-impl Range {
-    fn new(start: usize, end: usize) -> Self {
-        Range { start, end }
+    #[test]
+    fn test_inverse() {
+        let almanac = Day5Solver {}.parse(&example(2023, 5, Part::One)).unwrap();
+        let evaluator = &almanac.evaluator;
+        let location_to_seed = evaluator.inverse.as_ref().unwrap();
+        for (seed, location) in [(79, 82), (14, 43), (55, 86), (13, 35)] {
+            assert_eq!(location_to_seed.apply(location), Some(seed));
+        }
+        // Seeds 82 through 85 of the walkthrough's part 2 land on location 46 and the next three.
+        assert_eq!(evaluator.seeds_landing_in(&Interval::new(46, 49).into()), Interval::new(82, 85).into());
+        assert_eq!(evaluator.evaluate(&Interval::new(82, 85).into()), Interval::new(46, 49).into());
+    }
+
+    #[test]
+    fn test_location_search_strategies_agree() {
+        let almanac = Day5Solver {}.parse(&example(2023, 5, Part::One)).unwrap();
+        for start in 0..100 {
+            for len in [1, 2, 7, 30] {
+                let seeds = Interval::new(start, start + len - 1).into();
+                assert_eq!(
+                    almanac.evaluator.min_location(&seeds, LocationSearch::Forward),
+                    almanac.evaluator.min_location(&seeds, LocationSearch::Inverse),
+                    "seeds {start} to {}", start + len - 1
+                );
+            }
+        }
+        let almanac = Day5Solver {}.parse(&require_input!(2023, 5)).unwrap();
+        let seeds = almanac.seeds.chunks(2).map(|c| Interval::new(c[0], c[0] + c[1] - 1)).collect();
+        assert_eq!(
+            almanac.evaluator.min_location(&seeds, LocationSearch::Forward),
+            almanac.evaluator.min_location(&seeds, LocationSearch::Inverse),
+        );
     }
-    fn overlaps(&self, other: &Self) -> bool {
-        self.contains(other.start)
-            || self.contains(other.end)
-            || other.contains(self.start)
-            || other.contains(self.end)
+
+    #[test]
+    fn test_inverse_search_without_bijection() {
+        // Seeds 0 to 4 and 5 to 9 all land on locations 5 to 9, so the maps can't be inverted.
+        let combiner = chain(vec![PiecewiseShift::new(vec![(Interval::new(0, 4), 5)]).unwrap()]);
+        assert!(combiner.inverse.is_none());
+        let seeds = Interval::new(2, 3).into();
+        assert_eq!(combiner.min_location(&seeds, LocationSearch::Inverse).unwrap(), 7);
     }
 
-    fn contains(&self, val: usize) -> bool {
-        self.start <= val && val <= self.end
+    #[test]
+    fn test_ranges_past_usize_max() {
+        let max = usize::MAX;
+        let parse = |map: String| Day5Solver {}.parse(&format!("seeds: 1 1\n\nseed-to-location map:\n{map}"));
+        let err = parse(format!("{} 0 3", max - 1)).unwrap_err();
+        assert_eq!(err.to_string(), format!("line 4: parsing map range: destination range {} 3 ends past {max}", max - 1));
+        let err = parse(format!("0 {} 3", max - 1)).unwrap_err();
+        assert_eq!(err.to_string(), format!("line 4: parsing map range: source range {} 3 ends past {max}", max - 1));
+        let err = parse("0 5 0".to_string()).unwrap_err();
+        assert_eq!(err.to_string(), "line 4: parsing map range: map range can't be empty");
+
+        // Ranges that end right at usize::MAX are fine, in both directions.
+        let almanac = parse(format!("{} 0 2\n0 {} 2", max - 1, max - 1)).unwrap();
+        assert_eq!(Day5Solver {}.solve_part_1(&almanac).unwrap(), max.to_string().as_str());
+        let almanac = Day5Solver {}.parse(&format!("seeds: {} 2\n\nseed-to-location map:\n0 {} 2", max - 1, max - 1)).unwrap();
+        assert_eq!(Day5Solver {}.solve_part_2(&almanac).unwrap(), "0");
+        let almanac = Day5Solver {}.parse(&format!("seeds: {max} 2\n\nseed-to-location map:\n0 1 2")).unwrap();
+        let err = Day5Solver {}.solve_part_2(&almanac).unwrap_err();
+        assert_eq!(err.to_string(), format!("line 1: parsing seeds: seed range {max} 2 ends past {max}"));
     }
 
-    fn intersect(&self, other: &Self) -> Option<Self> {
-        if !self.overlaps(other) {
-            return None;
+    #[test]
+    fn test_layers_keep_the_number_of_values() {
+        // Random layers squeezed against both ends of usize, checking that every range maps to
+        // ranges that hold exactly as many values as it does.
+        let mut rng = Rng::new(5);
+        for _ in 0..500 {
+            let near_end = |rng: &mut Rng| match rng.below(3) {
+                0 => rng.below(64) as usize,
+                1 => usize::MAX - rng.below(64) as usize,
+                _ => rng.next() as usize,
+            };
+            let mut pieces = vec![];
+            for _ in 0..rng.below(4) {
+                let length = rng.below(40) as usize + 1;
+                let map_line = MapLine { dest_start: near_end(&mut rng), source_start: near_end(&mut rng), length };
+                if let Ok(piece) = map_line.to_piece() {
+                    pieces.push(piece);
+                }
+            }
+            let Ok(layer) = PiecewiseShift::new(pieces) else {
+                continue;
+            };
+            let start = near_end(&mut rng);
+            let Some(input) = Interval::with_len(start, rng.below(100) as u128 + 1) else {
+                continue;
+            };
+            let output = layer.apply_interval(input).expect("pieces were checked to fit in a usize");
+            assert_eq!(output.iter().map(|range| range.len()).sum::<u128>(), input.len(), "{layer} {input}");
         }
+    }
+
+    #[test]
+    fn test_overlapping_map_ranges() {
+        let solver = Day5Solver {};
+        let err = solver.parse(&[
+            "seeds: 79 14 55 13",
+            "",
+            "seed-to-soil map:",
+            "50 98 2",
+            "52 50 49",
+        ].join("\n")).unwrap_err();
+        assert_eq!(err.to_string(), "line 5: parsing map range: source range [50, 98] overlaps [98, 99] on line 4");
+    }
+
+    #[test]
+    fn test_any_category_conversion() {
+        let almanac = Day5Solver {}.parse(&example(2023, 5, Part::One)).unwrap();
+        // Seed 79 has soil 81 and humidity 78 in the puzzle's walkthrough, and seed 14 has
+        // fertilizer 53 and light 42.
+        let seed_to_humidity = almanac.combiner("seed", "humidity").unwrap();
+        assert_eq!(seed_to_humidity.evaluate(&Interval::point(79).into()), Interval::point(78).into());
+        assert_eq!(
+            almanac.combiner("seed", "soil").unwrap().evaluate(&Interval::point(79).into()),
+            Interval::point(81).into()
+        );
+        let fertilizer_to_light = almanac.combiner("fertilizer", "light").unwrap();
+        assert_eq!(fertilizer_to_light.evaluate(&Interval::point(53).into()), Interval::point(42).into());
+        assert_eq!(
+            almanac.combiner("seed", "seed").unwrap().evaluate(&Interval::point(5).into()),
+            Interval::point(5).into()
+        );
+
+        let err = almanac.combiner("location", "seed").unwrap_err();
+        assert_eq!(err.to_string(), "no map from location on the way from location to seed");
+        let err = almanac.combiner("soil", "seed").unwrap_err();
+        assert_eq!(err.to_string(), "no map from location on the way from soil to seed");
+    }
 
-        // Since they overlap their overlap must be the max of the starts and the min of the ends
-        Some(Self::new(
-            std::cmp::max(self.start, other.start),
-            std::cmp::min(self.end, other.end),
-        ))
+    #[test]
+    fn test_seed_events() {
+        let solver = Day5Solver {};
+        let almanac = solver.parse(&example(2023, 5, Part::One)).unwrap();
+        let mut events = vec![];
+        assert_eq!(solver.solve_explained(&almanac, Part::One, &mut events).unwrap(), "35");
+        let lines = events.iter().map(|event| event.to_string()).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "seed number=79 soil=81 fertilizer=81 water=81 light=74 temperature=78 humidity=78 location=82");
+        assert_eq!(lines[1], "seed number=14 soil=14 fertilizer=53 water=49 light=42 temperature=42 humidity=43 location=43");
+    }
+
+    #[test]
+    fn test_map_structure() {
+        let parse = |maps: &[&str]| Day5Solver {}.parse(&format!("seeds: 1 2\n\n{}", maps.join("\n\n")));
+        // The blocks can come in any order.
+        let almanac = parse(&["soil-to-location map:\n10 0 5", "seed-to-soil map:\n0 1 1"]).unwrap();
+        assert_eq!(Day5Solver {}.solve_part_1(&almanac).unwrap(), "10");
+
+        let err = parse(&["seed-to-soil map:", "soil-to-water map:"]).unwrap_err();
+        assert_eq!(err.to_string(), "no map from water on the way from seed to location");
+        let err = parse(&["seed-to-soil map:", "soil-to-seed map:"]).unwrap_err();
+        assert_eq!(err.to_string(), "maps from seed loop back to seed without reaching location");
+        let err = parse(&["seed-to-soil map:", "seed-to-location map:"]).unwrap_err();
+        assert_eq!(err.to_string(), "line 5: parsing map header: there is already a map from seed on line 3");
+        let err = parse(&["seed-to-soil:"]).unwrap_err();
+        assert_eq!(err.line, Some(3));
+        assert_eq!(err.context.as_deref(), Some("parsing map header"));
+    }
+
+    #[test]
+    fn test_part_1() {
+        let solver = Day5Solver {};
+        let input = solver.parse(&require_input!(2023, 5)).unwrap();
+        let result = solver.solve_part_1(&input);
+        assert_eq!(result.unwrap(), "551761867");
+    }
+
+    #[test]
+    fn test_part_2() {
+        let solver = Day5Solver {};
+        let input = solver.parse(&require_input!(2023, 5)).unwrap();
+        let result = solver.solve_part_2(&input);
+        assert_eq!(result.unwrap(), "57451709");
     }
 
-    fn shift(&self, delta: isize) -> Range {
-        Range::new(
-            (self.start as isize + delta) as usize,
-            (self.end as isize + delta) as usize,
-        )
+    #[test]
+    fn test_malformed_map_range() {
+        let solver = Day5Solver {};
+        let err = solver.parse(&[
+            "seeds: 79 14 55 13",
+            "",
+            "seed-to-soil map:",
+            "50 98 2",
+            "52 50 48",
+            "",
+            "soil-to-fertilizer map:",
+            "0 15",
+        ].join("\n")).unwrap_err();
+        assert_eq!(err.line, Some(8));
+        assert_eq!(err.context.as_deref(), Some("parsing map range"));
     }
 }
 
+/// MapLine is a `<destination start> <source start> <length>` line of a map.
 #[derive(Debug, Copy, Clone, PartialEq)]
-struct MapRange {
-    range: Range,
-    delta: isize,
+struct MapLine {
+    dest_start: usize,
+    source_start: usize,
+    length: usize,
 }
 
-impl MapRange {
-    fn new(start: usize, end: usize, delta: isize) -> Self {
-        MapRange {
-            range: Range::new(start, end),
-            delta,
-        }
-    }
-
-    fn evaluate_range(&self, range: &Range) -> (Vec<Range>, Option<Range>) {
-        let mut out = vec![];
-        if let Some(intersection) = self.range.intersect(range) {
-            // The part of the range that is to the left of the intersection maps to itself
-            if range.start != intersection.start {
-                out.push(Range::new(range.start, intersection.start - 1));
-            }
-            // Take the intersection and shift by delta
-            out.push(intersection.shift(self.delta));
-            // Return what it mapped to
-            let remaining = (intersection.end != range.end)
-                .then_some(Range::new(intersection.end + 1, range.end));
-            return (out, remaining);
+impl MapLine {
+    /// Returns the source range and the offset that takes it to the destination. Both ranges have
+    /// to fit in a usize, and the offset is an i128 so that it can't overflow either.
+    fn to_piece(self) -> Result<(Interval<usize>, i128), SolveError> {
+        let MapLine { dest_start, source_start, length } = self;
+        if length == 0 {
+            return Err(SolveError::new("map range can't be empty"));
         }
-        (out, Some(*range))
+        let source = Interval::with_len(source_start, length as u128)
+            .ok_or_else(|| SolveError::new(format!("source range {source_start} {length} ends past {}", usize::MAX)))?;
+        Interval::with_len(dest_start, length as u128)
+            .ok_or_else(|| SolveError::new(format!("destination range {dest_start} {length} ends past {}", usize::MAX)))?;
+        Ok((source, dest_start as i128 - source_start as i128))
     }
 }
 
-fn parse_range_mapper(line: &str) -> IResult<&str, MapRange> {
+fn parse_range_mapper(line: &str) -> IResult<&str, MapLine> {
     let (line, dest_start) = parse_number(line)?;
     let (line, _) = space1(line)?;
     let (line, source_start) = parse_number(line)?;
     let (line, _) = space1(line)?;
     let (line, length) = parse_number(line)?;
-    Ok((
-        line,
-        MapRange::new(
-            source_start,
-            source_start + length - 1,
-            dest_start as isize - source_start as isize,
-        ),
-    ))
+    Ok((line, MapLine { dest_start, source_start, length }))
 }
 
+/// LocationSearch is how the lowest location of a set of seeds is found.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LocationSearch {
+    /// Map the seeds to their locations and take the lowest.
+    Forward,
+    /// Walk the locations from the lowest up through the inverse map and stop at the first one
+    /// whose seed was planted. Falls back to Forward when the maps can't be inverted.
+    Inverse,
+}
+
+/// MapRangeCombiner chains the maps of the almanac, each one a piece-wise function
+/// F(x) = {x + A if x in [a, b], x + B if x in [c, d], ..., x otherwise}. Composing piece-wise
+/// functions of this shape gives another one, so the chain is folded up front into a single map
+/// that takes a seed straight to its location. Chains between other categories are combined the
+/// same way.
 #[derive(Debug)]
-struct MapRangeLayer {
-    // A MapRangeLayer is a collection of non-overlapping MapRangeLayers. This can be interpreted
-    // as a piece-wise function F(x) = {x + A if x in [a, b], x + B if x in [c, d], ...}
-    ranges: Vec<MapRange>,
+pub struct MapRangeCombiner {
+    forward: PiecewiseShift<usize>,
+    /// None when two values share an image, e.g. two seeds the same location, which the puzzle
+    /// doesn't rule out.
+    inverse: Option<PiecewiseShift<usize>>,
 }
 
-impl MapRangeLayer {
-    /// mapped_ranges returns the ranges that the given range maps to. Note that this may be
-    /// one or more, since the MapRangeLayer has piece-wise functions and the initial range may be
-    /// split.
-    fn evaluate_range(&self, range: &Range) -> Vec<Range> {
-        let mut out = vec![];
-        let mut remaining = *range;
-        for r in self.ranges.iter() {
-            let (intersection, not_intersected) = r.evaluate_range(&remaining);
-            // We are no longer in the range that has intersected. Exit early
-            out.extend(intersection);
-            if not_intersected.is_none() {
-                break;
-            }
-            remaining = not_intersected.unwrap();
+impl MapRangeCombiner {
+    fn new(path: &[(&str, &CategoryMap)]) -> Self {
+        let mut forward = PiecewiseShift::identity();
+        for (_, map) in path {
+            forward = forward
+                .then(&map.function)
+                .expect("MapLine::to_piece keeps every destination range within a usize");
         }
-        out
+        let inverse = forward.inverse();
+        MapRangeCombiner { forward, inverse }
     }
-    fn from_ranges(ranges: Vec<MapRange>) -> Self {
-        let mut new_ranges = vec![];
-        // Sort ranges by start asc
-        let mut ranges = ranges;
-        ranges.sort_by(|a, b| a.range.start.cmp(&b.range.start));
-
-        let mut start_index = 0;
-        for range in ranges {
-            if start_index < range.range.start {
-                new_ranges.push(MapRange::new(start_index, range.range.start - 1, 0));
-            }
-            new_ranges.push(range);
-            start_index = range.range.end + 1;
-        }
-        // Add the last range that goes to Inf. This is allowed because we
-        // don't expect the caller to set it to inf.
-        new_ranges.push(MapRange::new(start_index, usize::MAX, 0));
-        Self { ranges: new_ranges }
+
+    fn apply(&self, value: usize) -> usize {
+        self.forward
+            .apply(value)
+            .expect("MapLine::to_piece keeps every destination range within a usize")
     }
-}
 
-#[derive(Debug)]
-struct MapRangeCombiner {
-    mappers: Vec<MapRangeLayer>,
-}
+    /// Returns every location the given seeds map to. Ranges are split wherever they cross the
+    /// edge of a range of the composed map, so a range may map to several.
+    fn evaluate(&self, seeds: &IntervalSet<usize>) -> IntervalSet<usize> {
+        self.forward
+            .apply_set(seeds)
+            .expect("MapLine::to_piece keeps every destination range within a usize")
+    }
 
-impl MapRangeCombiner {
-    fn evaluate_range(&self, range: &Range, map_index: usize) -> Vec<Range> {
-        // If there are no more mappers the range maps to itself
-        if map_index >= self.mappers.len() {
-            return vec![*range];
-        }
-        let mut out = vec![];
-        if let Some(current_mapper) = self.mappers.get(map_index) {
-            // For the current mapper evaluate the range and return all the new values we found.
-            for new_mapped_range in current_mapper.evaluate_range(range).iter() {
-                out.extend(self.evaluate_range(new_mapped_range, map_index + 1));
+    /// Returns every seed that ends up in one of the given locations.
+    fn seeds_landing_in(&self, locations: &IntervalSet<usize>) -> IntervalSet<usize> {
+        self.forward.preimage_set(locations)
+    }
+
+    fn min_location(&self, seeds: &IntervalSet<usize>, search: LocationSearch) -> Result<usize, SolveError> {
+        let min = match (search, &self.inverse) {
+            (LocationSearch::Inverse, Some(inverse)) => {
+                inverse.split(Interval::full()).into_iter().find_map(|(locations, _)| {
+                    // Within a range of the map every location is its seed shifted by the same
+                    // amount, so the lowest planted seed gives the lowest location.
+                    let planted = self.seeds_landing_in(&locations.into()).intersection(seeds);
+                    planted.min().and_then(|seed| self.forward.apply(seed))
+                })
             }
-        }
-        out
+            _ => self.evaluate(seeds).min(),
+        };
+        min.ok_or_else(|| SolveError::new("no seeds"))
+    }
+}
+
+/// Prints the composed map, one range per line.
+impl fmt::Display for MapRangeCombiner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.forward)
     }
 }
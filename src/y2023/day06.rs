@@ -1,53 +1,77 @@
-use crate::solver::Solver;
+use crate::solver::{Answer, Solver, SolveError};
+use crate::utils::parsers::{parse_line, parse_number};
+use nom::bytes::complete::tag;
+use nom::character::complete::{digit1, space1};
+use nom::combinator::map_res;
+use nom::IResult;
+use nom::multi::separated_list1;
 use std::iter::zip;
+use crate::registry::SolverRegistry;
 
 pub struct Day6Solver {}
 
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 6, Box::new(Day6Solver {}));
+}
+
 impl Solver for Day6Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String {
-        let times = parse_race_info(
-            lines[0]
-                .splitn(2, "Time:")
-                .collect::<Vec<_>>()
-                .get(1)
-                .unwrap(),
-        );
-        let distances_to_beat = parse_race_info(
-            lines[1]
-                .splitn(2, "Distance:")
-                .collect::<Vec<_>>()
-                .get(1)
-                .unwrap(),
-        );
-        let race_info = zip(times, distances_to_beat)
+    type Input = Races;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        let lines = input.lines().collect::<Vec<_>>();
+        let (time, distance) = (line_at(&lines, 0), line_at(&lines, 1));
+        let times = parse_line(0, time, "parsing times", |l| parse_race_info(l, "Time:"))?;
+        let distances_to_beat = parse_line(1, distance, "parsing distances", |l| parse_race_info(l, "Distance:"))?;
+        if times.len() != distances_to_beat.len() {
+            return Err(SolveError::new(format!(
+                "found {} times but {} distances",
+                times.len(),
+                distances_to_beat.len()
+            )));
+        }
+        let races = zip(times, distances_to_beat)
             .map(|(t, d)| RaceInfo {
                 race_duration_in_seconds: t,
                 distance_to_beat: d,
             })
-            .collect::<Vec<RaceInfo>>();
+            .collect();
+        let kerned_race = RaceInfo {
+            race_duration_in_seconds: parse_line(0, time, "parsing kerned time", |l| parse_kerned_race_info(l, "Time:"))?,
+            distance_to_beat: parse_line(1, distance, "parsing kerned distance", |l| parse_kerned_race_info(l, "Distance:"))?,
+        };
+        Ok(Races { races, kerned_race })
+    }
 
+    fn solve_part_1(&self, races: &Self::Input) -> Result<Answer, SolveError> {
         let mut product = 1;
-        for race in race_info {
+        for race in races.races.iter() {
             let strat = LinearSpeedStrategy {
-                race_info: race,
+                race_info: *race,
             };
-            product *= strat.winning_charge_durations().len();
+            product *= strat.winning_count();
         }
-        product.to_string()
+        Ok(product.into())
     }
 
-    fn solve_part_2(&self, _: Vec<String>) -> String {
-        // Not bothering to parse
-        let race_info = RaceInfo {
-            race_duration_in_seconds: 61677571,
-            distance_to_beat: 430103613071150,
-        };
-        LinearSpeedStrategy{race_info}.winning_charge_durations().len().to_string()
+    fn solve_part_2(&self, races: &Self::Input) -> Result<Answer, SolveError> {
+        let race_info = races.kerned_race;
+        Ok(LinearSpeedStrategy{race_info}.winning_count().into())
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-struct RaceInfo {
+fn line_at<'a>(lines: &[&'a str], idx: usize) -> &'a str {
+    lines.get(idx).copied().unwrap_or_default()
+}
+
+/// Races holds the races of the sheet read column by column, and the single race that the sheet
+/// describes once the spaces between the numbers are ignored.
+pub struct Races {
+    races: Vec<RaceInfo>,
+    kerned_race: RaceInfo,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RaceInfo {
     race_duration_in_seconds: usize,
     distance_to_beat: usize,
 }
@@ -57,38 +81,151 @@ struct LinearSpeedStrategy {
 }
 
 impl LinearSpeedStrategy {
+    /// Returns the shortest and longest hold that beat the record, or None if no hold does.
+    ///
+    /// Holding for h out of T seconds covers (T - h) * h, so the winning holds are the integers
+    /// strictly between the roots of h² - Th + d = 0, that is (T ± √(T² - 4d)) / 2. The roots are
+    /// found with an integer square root so that large races don't lose precision in a float, and
+    /// the floored guess is then nudged onto the first winning hold. When a root is an exact
+    /// integer it ties the record and is skipped by the nudge. The winning holds are symmetric
+    /// around T / 2, so the longest one mirrors the shortest.
+    fn winning_hold_range(&self) -> Option<(usize, usize)> {
+        let duration = self.race_info.race_duration_in_seconds as u128;
+        let distance = self.race_info.distance_to_beat as u128;
+        let discriminant = (duration * duration).checked_sub(4 * distance)?;
+        let mut lo = (duration - discriminant.isqrt()) / 2;
+        while lo > 0 && self.wins(lo - 1) {
+            lo -= 1;
+        }
+        while lo <= duration / 2 && !self.wins(lo) {
+            lo += 1;
+        }
+        if lo > duration / 2 {
+            return None;
+        }
+        Some((lo as usize, (duration - lo) as usize))
+    }
+
+    /// Returns the number of holds that beat the record.
+    fn winning_count(&self) -> usize {
+        self.winning_hold_range().map_or(0, |(lo, hi)| hi - lo + 1)
+    }
+
+    fn wins(&self, hold: u128) -> bool {
+        let duration = self.race_info.race_duration_in_seconds as u128;
+        (duration - hold) * hold > self.race_info.distance_to_beat as u128
+    }
+
+    /// Tries every hold. Only used to check the closed form.
+    #[cfg(test)]
     fn winning_charge_durations(&self) -> Vec<usize> {
-        let mut out = vec![];
-        // I know that this can be solved by using 
-        // (T-h)*h - d > 0
-        // where 
-        // T = time for race
-        // h = hold time
-        // d = distance to beat
-        // and this is a quadratic. You could optimize this in a few ways
-        // 1. Find the zeros and count the distance between them
-        // 2. Start from the ends (hold for 1 second, hold for T -1 second) and stop
-        //    counting when you find the regions where you stop losing.
-        // However, I thought we might have to do something interesting with the outcomes
-        // so I just did this and it's fast enough.
-        for i in 1..self.race_info.race_duration_in_seconds {
-            let d = self.distance_for_hold_duration(i);
-            if d > self.race_info.distance_to_beat as isize {
-                out.push(i);
+        (1..self.race_info.race_duration_in_seconds)
+            .filter(|&hold| self.distance_for_hold_duration(hold) > self.race_info.distance_to_beat)
+            .collect()
+    }
+
+    #[cfg(test)]
+    fn distance_for_hold_duration(&self, hold: usize) -> usize {
+        (self.race_info.race_duration_in_seconds - hold) * hold
+    }
+}
+
+fn parse_race_info<'a>(line: &'a str, label: &str) -> IResult<&'a str, Vec<usize>> {
+    let (line, _) = tag(label)(line)?;
+    let (line, _) = space1(line)?;
+    separated_list1(space1, parse_number)(line)
+}
+
+/// Reads the numbers after the label as a single number, ignoring the spaces between them.
+fn parse_kerned_race_info<'a>(line: &'a str, label: &str) -> IResult<&'a str, usize> {
+    let (line, _) = tag(label)(line)?;
+    let (line, _) = space1(line)?;
+    map_res(separated_list1(space1, digit1), |digits: Vec<&str>| digits.concat().parse::<usize>())(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Part;
+    use crate::test_support::{example, require_input};
+
+    #[test]
+    fn test_parse_kerned_race() {
+        let races = Day6Solver {}.parse(&example(2023, 6, Part::Two)).unwrap();
+        assert_eq!(races.races.len(), 3);
+        assert_eq!(
+            races.kerned_race,
+            RaceInfo {
+                race_duration_in_seconds: 71530,
+                distance_to_beat: 940200,
             }
+        );
+    }
+
+    #[test]
+    fn test_parse_mismatched_columns() {
+        let err = Day6Solver {}.parse("Time:      7  15   30\nDistance:  9  40").err().unwrap();
+        assert_eq!(err.to_string(), "found 3 times but 2 distances");
+    }
+
+    fn strategy(race_duration_in_seconds: usize, distance_to_beat: usize) -> LinearSpeedStrategy {
+        LinearSpeedStrategy {
+            race_info: RaceInfo {
+                race_duration_in_seconds,
+                distance_to_beat,
+            },
         }
-        out
     }
 
-    fn distance_for_hold_duration(&self, hold: usize) -> isize {
-        let dur = self.race_info.race_duration_in_seconds as isize;
-        let hold = hold as isize;
-        (dur - hold) * hold
+    #[test]
+    fn test_winning_hold_range() {
+        assert_eq!(strategy(7, 9).winning_hold_range(), Some((2, 5)));
+        // Both roots are exact integers (10 and 20), and holding that long only ties the record.
+        assert_eq!(strategy(30, 200).winning_hold_range(), Some((11, 19)));
+        // The only hold that reaches the record ties it.
+        assert_eq!(strategy(10, 25).winning_hold_range(), None);
+        assert_eq!(strategy(10, 24).winning_hold_range(), Some((5, 5)));
+        assert_eq!(strategy(10, 1000).winning_hold_range(), None);
+        assert_eq!(strategy(0, 0).winning_hold_range(), None);
+        assert_eq!(strategy(1, 0).winning_hold_range(), None);
+        assert_eq!(strategy(2, 0).winning_hold_range(), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_closed_form_matches_brute_force() {
+        for duration in 0..60 {
+            for distance in 0..=duration * duration / 4 + 1 {
+                let strat = strategy(duration, distance);
+                let winners = strat.winning_charge_durations();
+                let expected = winners.first().map(|&lo| (lo, *winners.last().unwrap()));
+                assert_eq!(strat.winning_hold_range(), expected, "race of {duration}ms to beat {distance}mm");
+                assert_eq!(strat.winning_count(), winners.len());
+            }
+        }
     }
-}
 
-fn parse_race_info(line: &str) -> Vec<usize> {
-    line.split_ascii_whitespace()
-        .map(|x| x.parse::<usize>().unwrap())
-        .collect::<Vec<_>>()
+    #[test]
+    fn test_large_race() {
+        // T² is far beyond what an f64 represents exactly.
+        let strat = strategy(4_000_000_000, 3_999_999_999_000_000_000);
+        let (lo, hi) = strat.winning_hold_range().unwrap();
+        assert!(strat.wins(lo as u128) && !strat.wins(lo as u128 - 1));
+        assert!(strat.wins(hi as u128) && !strat.wins(hi as u128 + 1));
+        assert_eq!(lo + hi, 4_000_000_000);
+    }
+
+    #[test]
+    fn test_example() {
+        let solver = Day6Solver {};
+        let races = solver.parse(&example(2023, 6, Part::One)).unwrap();
+        assert_eq!(solver.solve_part_1(&races).unwrap(), "288");
+        assert_eq!(solver.solve_part_2(&races).unwrap(), "71503");
+    }
+
+    #[test]
+    fn test_part_2() {
+        let solver = Day6Solver {};
+        let races = solver.parse(&require_input!(2023, 6)).unwrap();
+        assert_eq!(solver.solve_part_2(&races).unwrap(), "45647654");
+    }
 }
@@ -1,85 +1,142 @@
-use crate::solver::Solver;
+use crate::solver::{Answer, Discard, Event, EventSink, Part, Solver, SolveError};
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::iter::zip;
-use crate::y2023::day07::RuleSet::{Simple, WithJokers};
+use crate::registry::SolverRegistry;
 
 pub struct Day7Solver {}
 
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 7, Box::new(Day7Solver {}));
+}
+
 impl Solver for Day7Solver {
-    fn solve_part_1(&self, lines: Vec<String>) -> String {
-        let mut hands = vec![];
-        for line in lines {
-            let parts = line.split(' ').collect::<Vec<_>>();
-            let poker_hand = CamelPokerHand::new_with_rules(parts[0].to_string(), Simple);
-            let bid = parts[1].parse::<usize>().unwrap();
-            hands.push(CamelPokerRound {
-                hand: poker_hand,
-                bid,
-            });
-        }
+    type Input = Vec<(String, usize)>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| parse_round(idx, line).map(|(hand, bid)| (hand.to_string(), bid)))
+            .collect()
+    }
 
-        hands.sort_by(|a, b| a.hand.cmp(&b.hand));
-        let mut out = 0;
-        for (mult, hand) in hands.iter().enumerate() {
-            out += (mult + 1) * hand.bid;
-        }
-        out.to_string()
-    }
-
-    fn solve_part_2(&self, lines: Vec<String>) -> String {
-        let mut hands = vec![];
-        for line in lines {
-            let parts = line.split(' ').collect::<Vec<_>>();
-            let poker_hand = CamelPokerHand::new_with_rules(parts[0].to_string(), WithJokers);
-            let bid = parts[1].parse::<usize>().unwrap();
-            hands.push(CamelPokerRound {
-                hand: poker_hand,
-                bid,
-            });
-        }
+    fn solve_part_1(&self, rounds: &Self::Input) -> Result<Answer, SolveError> {
+        self.solve_explained(rounds, Part::One, &mut Discard)
+    }
+
+    fn solve_part_2(&self, rounds: &Self::Input) -> Result<Answer, SolveError> {
+        self.solve_explained(rounds, Part::Two, &mut Discard)
+    }
+
+    fn solve_explained(&self, rounds: &Self::Input, part: Part, events: &mut dyn EventSink) -> Result<Answer, SolveError> {
+        let winnings = match part {
+            Part::One => total_winnings(rounds, &Standard, events),
+            Part::Two => total_winnings(rounds, &JokersWild, events),
+        };
+        Ok(winnings.into())
+    }
+}
+
+/// Reads every hand under the given rules and sorts them from the weakest to the strongest, so
+/// that a hand's rank is its position plus one. Equal hands keep the order they were dealt in.
+fn ranked_rounds<'a>(rounds: &'a [(String, usize)], rules: &impl Rules) -> Vec<CamelPokerRound<'a>> {
+    let mut hands = vec![];
+    for (hand, bid) in rounds {
+        let poker_hand = CamelPokerHand::new(hand, rules);
+        hands.push(CamelPokerRound {
+            text: hand,
+            hand: poker_hand,
+            bid: *bid,
+        });
+    }
+    hands.sort_by_key(|round| round.hand.sort_key);
+    hands
+}
 
-        hands.sort_by(|a, b| a.hand.cmp(&b.hand));
-        let mut out = 0;
-        for (mult, hand) in hands.iter().enumerate() {
-            out += (mult + 1) * hand.bid;
+/// Ranks every hand under the given rules and adds up each bid multiplied by its rank. Every hand
+/// is reported from the weakest to the strongest with its level, what its wild cards were played
+/// as and what it adds to the winnings.
+fn total_winnings(rounds: &[(String, usize)], rules: &impl Rules, events: &mut dyn EventSink) -> usize {
+    let mut out = 0;
+    for (mult, round) in ranked_rounds(rounds, rules).iter().enumerate() {
+        let winnings = (mult + 1) * round.bid;
+        out += winnings;
+        if events.enabled() {
+            let mut event = Event::new("hand")
+                .with("rank", mult + 1)
+                .with("cards", round.text)
+                .with("level", format!("{:?}", round.hand.level));
+            if let Some(substitute) = rules.wild_substitute(round.text) {
+                let played_as = round
+                    .text
+                    .chars()
+                    .map(|c| if rules.wild_cards().contains(c) { substitute } else { c })
+                    .collect::<String>();
+                event = event.with("played_as", played_as);
+            }
+            events.record(event.with("bid", round.bid).with("winnings", winnings));
         }
-        out.to_string()
     }
+    out
+}
+
+/// Splits a line into the hand and the bid, checking that the hand is made of five valid cards.
+fn parse_round(line_idx: usize, line: &str) -> Result<(&str, usize), SolveError> {
+    let error = |message: String| {
+        SolveError::new(message)
+            .with_line(line_idx)
+            .with_context("parsing hand")
+    };
+    let (hand, bid) = line
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| error(format!("expected a hand and a bid, found {line:?}")))?;
+    if hand.chars().count() != 5 {
+        return Err(error(format!("poker hand must have 5 cards, found {hand:?}")));
+    }
+    if let Some(card) = hand.chars().find(|c| !"23456789TJQKA".contains(*c)) {
+        return Err(error(format!("invalid card {card:?} in hand {hand:?}")));
+    }
+    let bid = bid
+        .parse::<usize>()
+        .map_err(|err| error(format!("invalid bid {bid:?}: {err}")))?;
+    Ok((hand, bid))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::solver::Solver;
-    use crate::utils::lines::lines_from_file;
-    use crate::y2023::day07::{CamelPokerHand, Day7Solver};
+    use crate::test_support::require_input;
+    use std::time::Instant;
+    use crate::bench::Stats;
+    use crate::test_support::Rng;
+    use crate::y2023::day07::*;
 
     #[test]
     fn compare_hands() {
         // Equal hands
-        let hand_1 = CamelPokerHand::new("AAAKK".to_string());
-        let hand_2 = CamelPokerHand::new("AAAKK".to_string());
-        assert_eq!(hand_1 == hand_2, true);
+        let hand_1 = CamelPokerHand::new("AAAKK", &Standard);
+        let hand_2 = CamelPokerHand::new("AAAKK", &Standard);
+        assert!(hand_1 == hand_2);
 
         // Five of a kind is greater than full house
-        let hand_1 = CamelPokerHand::new("AAAAA".to_string());
-        let hand_2 = CamelPokerHand::new("AAAKK".to_string());
-        assert_eq!(hand_1 > hand_2, true);
+        let hand_1 = CamelPokerHand::new("AAAAA", &Standard);
+        let hand_2 = CamelPokerHand::new("AAAKK", &Standard);
+        assert!(hand_1 > hand_2);
 
         // Cards of equal level where the first card breaks the tie in favor of the second hand
-        let hand_1 = CamelPokerHand::new("KKAAA".to_string());
-        let hand_2 = CamelPokerHand::new("AAKKK".to_string());
-        assert_eq!(hand_1 < hand_2, true);
+        let hand_1 = CamelPokerHand::new("KKAAA", &Standard);
+        let hand_2 = CamelPokerHand::new("AAKKK", &Standard);
+        assert!(hand_1 < hand_2);
     }
 
     #[test]
     fn test_sorting() {
         let mut cards = vec![
-            CamelPokerHand::new("32T3K".to_string()),
-            CamelPokerHand::new("T55J5".to_string()),
-            CamelPokerHand::new("KK677".to_string()),
-            CamelPokerHand::new("KTJJT".to_string()),
-            CamelPokerHand::new("QQQJA".to_string()),
+            CamelPokerHand::new("32T3K", &Standard),
+            CamelPokerHand::new("T55J5", &Standard),
+            CamelPokerHand::new("KK677", &Standard),
+            CamelPokerHand::new("KTJJT", &Standard),
+            CamelPokerHand::new("QQQJA", &Standard),
         ];
 
         cards.sort();
@@ -88,59 +145,270 @@ mod tests {
         assert_eq!(
             cards,
             vec![
-                CamelPokerHand::new("QQQJA".to_string()),
-                CamelPokerHand::new("T55J5".to_string()),
-                CamelPokerHand::new("KK677".to_string()),
-                CamelPokerHand::new("KTJJT".to_string()),
-                CamelPokerHand::new("32T3K".to_string()),
+                CamelPokerHand::new("QQQJA", &Standard),
+                CamelPokerHand::new("T55J5", &Standard),
+                CamelPokerHand::new("KK677", &Standard),
+                CamelPokerHand::new("KTJJT", &Standard),
+                CamelPokerHand::new("32T3K", &Standard),
             ]
         );
     }
 
+    /// Returns every 5-card multiset of the deck, as hands with their cards in deck order.
+    fn all_hands(deck: &str) -> Vec<String> {
+        fn extend(deck: &[char], from: usize, hand: &mut String, out: &mut Vec<String>) {
+            if hand.len() == 5 {
+                out.push(hand.clone());
+                return;
+            }
+            for idx in from..deck.len() {
+                hand.push(deck[idx]);
+                extend(deck, idx, hand, out);
+                hand.pop();
+            }
+        }
+        let mut out = vec![];
+        extend(&deck.chars().collect::<Vec<_>>(), 0, &mut String::new(), &mut out);
+        out
+    }
+
+    /// Classifies a hand without wild cards from the sorted sizes of its groups of matching cards.
+    fn level_by_shape(hand: &str) -> PokerLevel {
+        let mut sizes = hand.chars().map(|c| hand.matches(c).count()).collect::<Vec<_>>();
+        sizes.sort();
+        match sizes[..] {
+            [5, 5, 5, 5, 5] => PokerLevel::FiveOfAKind,
+            [1, 4, 4, 4, 4] => PokerLevel::FourOfAKind,
+            [2, 2, 3, 3, 3] => PokerLevel::FullHouse,
+            [1, 1, 3, 3, 3] => PokerLevel::ThreeOfAKind,
+            [1, 2, 2, 2, 2] => PokerLevel::TwoPair,
+            [1, 1, 1, 2, 2] => PokerLevel::OnePair,
+            [1, 1, 1, 1, 1] => PokerLevel::HighCard,
+            _ => unreachable!("{hand} has 5 cards"),
+        }
+    }
+
+    /// Classifies a hand by trying cards in place of each wild card and keeping the best. Any
+    /// card that isn't in the hand yet does as well as any other, so only one of those is tried.
+    fn best_substitution(hand: &str, rules: &impl Rules) -> PokerLevel {
+        let Some(idx) = hand.find(|c| rules.wild_cards().contains(c)) else {
+            return level_by_shape(hand);
+        };
+        let natural = |c: &char| !rules.wild_cards().contains(*c);
+        let absent = rules.card_order().chars().filter(natural).find(|c| !hand.contains(*c));
+        hand.chars()
+            .filter(natural)
+            .chain(absent)
+            .map(|c| best_substitution(&format!("{}{c}{}", &hand[..idx], &hand[idx + 1..]), rules))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_every_standard_hand() {
+        let hands = all_hands(Standard.card_order());
+        assert_eq!(hands.len(), 6188);
+        let mut per_level = [0; 7];
+        for hand in hands.iter() {
+            let level = Standard.classify(hand);
+            assert_eq!(level, level_by_shape(hand), "{hand}");
+            per_level[level as usize] += 1;
+        }
+        // High card, one pair, two pair, three of a kind, full house, four and five of a kind.
+        assert_eq!(per_level, [1287, 2860, 858, 858, 156, 156, 13]);
+    }
+
+    #[test]
+    fn test_every_hand_with_jokers() {
+        for hand in all_hands(JokersWild.card_order()) {
+            assert_eq!(JokersWild.classify(&hand), best_substitution(&hand, &JokersWild), "{hand}");
+        }
+    }
+
+    /// Twos and jokers are both wild, and aces are the weakest card.
+    struct LowAcesTwosWild;
+
+    impl Rules for LowAcesTwosWild {
+        fn card_order(&self) -> &str {
+            "A23456789TJQK"
+        }
+
+        fn wild_cards(&self) -> &str {
+            "2J"
+        }
+    }
+
+    #[test]
+    fn test_rule_variants() {
+        for hand in all_hands(LowAcesTwosWild.card_order()) {
+            assert_eq!(LowAcesTwosWild.classify(&hand), best_substitution(&hand, &LowAcesTwosWild), "{hand}");
+        }
+        assert_eq!(LowAcesTwosWild.classify("2J2KQ"), PokerLevel::FourOfAKind);
+        assert!(CamelPokerHand::new("A3456", &LowAcesTwosWild) < CamelPokerHand::new("3A456", &LowAcesTwosWild));
+        assert!(CamelPokerHand::new("A3456", &Standard) > CamelPokerHand::new("3A456", &Standard));
+        // The joker breaks ties as the weakest card even though it makes the strongest hand.
+        assert_eq!(JokersWild.classify("JKKK2"), PokerLevel::FourOfAKind);
+        assert!(CamelPokerHand::new("JKKK2", &JokersWild) < CamelPokerHand::new("QQQQ2", &JokersWild));
+    }
+
+    /// Orders hands the way the puzzle describes them: by level, then card by card.
+    fn compare_by_cards(a: &CamelPokerHand, b: &CamelPokerHand) -> Ordering {
+        a.level.cmp(&b.level).then_with(|| a.cards.cmp(&b.cards))
+    }
+
+    fn random_hands(rng: &mut Rng, count: usize) -> Vec<String> {
+        let deck = Standard.card_order().chars().collect::<Vec<_>>();
+        (0..count)
+            .map(|_| (0..5).map(|_| deck[rng.below(deck.len() as u64) as usize]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_key_matches_card_order() {
+        let mut rng = Rng::new(7);
+        let hands = random_hands(&mut rng, 300);
+        for hand in hands.iter() {
+            for other in hands.iter() {
+                let (a, b) = (CamelPokerHand::new(hand, &JokersWild), CamelPokerHand::new(other, &JokersWild));
+                assert_eq!(a.cmp(&b), compare_by_cards(&a, &b), "{hand} {other}");
+            }
+        }
+    }
+
+    /// Compares sorting 1000 hands by their packed key against sorting them with a comparator
+    /// that walks the cards. Run it with
+    /// `cargo test --release bench_sort_key -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_sort_key() {
+        let mut rng = Rng::new(2023);
+        let hands = random_hands(&mut rng, 1000)
+            .iter()
+            .map(|hand| CamelPokerHand::new(hand, &JokersWild))
+            .collect::<Vec<_>>();
+        let time = |sort: &dyn Fn(&mut Vec<CamelPokerHand>)| {
+            let samples = (0..200)
+                .map(|_| {
+                    let mut hands = hands.clone();
+                    let start = Instant::now();
+                    sort(&mut hands);
+                    start.elapsed()
+                })
+                .collect::<Vec<_>>();
+            Stats::from_samples(&samples)
+        };
+        let by_cards = time(&|hands| hands.sort_by(compare_by_cards));
+        let by_key = time(&|hands| hands.sort_by_key(|hand| hand.sort_key));
+        println!("comparing cards: median {:?}, mean {:?}", by_cards.median, by_cards.mean);
+        println!("packed key:      median {:?}, mean {:?}", by_key.median, by_key.mean);
+    }
+
+    #[test]
+    fn test_equal_hands_keep_their_order() {
+        // Equal hands rank in the order they are dealt, which decides whose bid gets the higher
+        // rank. Enough of them that the sort can't get it right by accident.
+        let solver = Day7Solver {};
+        let rounds = (1..=64)
+            .map(|bid| format!("{} {bid}", if bid % 2 == 0 { "AAAAA" } else { "23456" }))
+            .collect::<Vec<_>>();
+        let input = solver.parse(&rounds.join("\n")).unwrap();
+        let mut events = vec![];
+        solver.solve_explained(&input, Part::One, &mut events).unwrap();
+        let bids = events.iter().map(|event| event.get("bid").unwrap().parse().unwrap()).collect::<Vec<usize>>();
+        let dealt = (1..=64).step_by(2).chain((2..=64).step_by(2)).collect::<Vec<_>>();
+        assert_eq!(bids, dealt);
+    }
+
+    #[test]
+    fn test_hand_events() {
+        let solver = Day7Solver {};
+        let input = solver.parse(&["32T3K 765", "T55J5 684", "KK677 28", "KTJJT 220", "QQQJA 483"].join("\n")).unwrap();
+        let mut events = vec![];
+        assert_eq!(solver.solve_explained(&input, Part::Two, &mut events), Ok(Answer::Integer(5905)));
+        let lines = events.iter().map(|event| event.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "hand rank=1 cards=32T3K level=OnePair bid=765 winnings=765",
+                "hand rank=2 cards=KK677 level=TwoPair bid=28 winnings=56",
+                "hand rank=3 cards=T55J5 level=FourOfAKind played_as=T5555 bid=684 winnings=2052",
+                "hand rank=4 cards=QQQJA level=FourOfAKind played_as=QQQQA bid=483 winnings=1932",
+                "hand rank=5 cards=KTJJT level=FourOfAKind played_as=KTTTT bid=220 winnings=1100",
+            ]
+        );
+
+        let mut events = vec![];
+        assert_eq!(solver.solve_explained(&input, Part::One, &mut events), Ok(Answer::Integer(6440)));
+        assert_eq!(events[4].get("cards"), Some("QQQJA"));
+        assert_eq!(events[4].get("level"), Some("ThreeOfAKind"));
+        assert_eq!(events[4].get("played_as"), None);
+    }
+
+    #[test]
+    fn test_wild_substitute() {
+        assert_eq!(JokersWild.wild_substitute("KTJJT"), Some('T'));
+        // Both pairs are as large, the kings are stronger.
+        assert_eq!(JokersWild.wild_substitute("KKJTT"), Some('K'));
+        assert_eq!(JokersWild.wild_substitute("JJJJJ"), Some('A'));
+        assert_eq!(JokersWild.wild_substitute("KKTTA"), None);
+        assert_eq!(Standard.wild_substitute("JJJJJ"), None);
+    }
+
     #[test]
     fn test_part_1_partial() {
-        let lines = vec![
-            "32T3K 765".to_string(),
-            "T55J5 684".to_string(),
-            "KK677 28".to_string(),
-            "KTJJT 220".to_string(),
-            "QQQJA 483 ".to_string(),
-        ];
         let solver = Day7Solver {};
-        assert_eq!(solver.solve_part_1(lines), "6440");
+        let input = solver.parse(&[
+            "32T3K 765",
+            "T55J5 684",
+            "KK677 28",
+            "KTJJT 220",
+            "QQQJA 483 ",
+        ].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "6440");
     }
 
     #[test]
     fn test_part_2_partial() {
-        let lines = vec![
-            "32T3K 765".to_string(),
-            "T55J5 684".to_string(),
-            "KK677 28".to_string(),
-            "KTJJT 220".to_string(),
-            "QQQJA 483 ".to_string(),
-        ];
         let solver = Day7Solver {};
-        assert_eq!(solver.solve_part_2(lines), "5905");
+        let input = solver.parse(&[
+            "32T3K 765",
+            "T55J5 684",
+            "KK677 28",
+            "KTJJT 220",
+            "QQQJA 483 ",
+        ].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "5905");
+    }
+
+    #[test]
+    fn test_malformed_hands() {
+        let solver = Day7Solver {};
+        let err = solver.parse("32T3K 765\nT55J 684").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: parsing hand: poker hand must have 5 cards, found \"T55J\"");
+        let err = solver.parse("32X3K 765").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: parsing hand: invalid card 'X' in hand \"32X3K\"");
+        let err = solver.parse("32T3K").unwrap_err();
+        assert_eq!(err.line, Some(1));
     }
 
     #[test]
     fn test_part_1() {
         let solver = Day7Solver {};
-        let lines = lines_from_file("inputs/2023-day07.txt");
-        let result = solver.solve_part_1(lines);
-        assert_eq!(result, "241344943");
+        let input = solver.parse(&require_input!(2023, 7)).unwrap();
+        let result = solver.solve_part_1(&input);
+        assert_eq!(result.unwrap(), "241344943");
     }
 
     #[test]
     fn test_part_2() {
         let solver = Day7Solver {};
-        let lines = lines_from_file("inputs/2023-day07.txt");
-        let result = solver.solve_part_2(lines);
-        assert_eq!(result, "243101568");
+        let input = solver.parse(&require_input!(2023, 7)).unwrap();
+        let result = solver.solve_part_2(&input);
+        assert_eq!(result.unwrap(), "243101568");
     }
 }
 
-#[derive(Eq, PartialEq, PartialOrd, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Copy, Clone)]
 enum PokerLevel {
     HighCard,
     OnePair,
@@ -151,179 +419,144 @@ enum PokerLevel {
     FiveOfAKind,
 }
 
-#[derive(Eq, PartialEq, PartialOrd, Debug, Copy, Clone)]
-enum CardRank {
-    Joker,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
-}
-
-impl CardRank {
-    fn parse_rank(value: String, rule_set: RuleSet) -> Self {
-        match value.as_str() {
-            "J" => match rule_set {
-                RuleSet::Simple => Self::Jack,
-                RuleSet::WithJokers => Self::Joker,
-            },
-            "2" => Self::Two,
-            "3" => Self::Three,
-            "4" => Self::Four,
-            "5" => Self::Five,
-            "6" => Self::Six,
-            "7" => Self::Seven,
-            "8" => Self::Eight,
-            "9" => Self::Nine,
-            "T" => Self::Ten,
-            "Q" => Self::Queen,
-            "K" => Self::King,
-            "A" => Self::Ace,
-            _ => panic!("invalid value for card {value}"),
+impl PokerLevel {
+    /// Returns the level of a hand whose two largest groups of matching cards have the given
+    /// sizes. The second group is 0 when all the cards match.
+    fn from_groups(largest: u8, second: u8) -> Self {
+        match (largest, second) {
+            (5, _) => PokerLevel::FiveOfAKind,
+            (4, _) => PokerLevel::FourOfAKind,
+            (3, 2) => PokerLevel::FullHouse,
+            (3, _) => PokerLevel::ThreeOfAKind,
+            (2, 2) => PokerLevel::TwoPair,
+            (2, _) => PokerLevel::OnePair,
+            _ => PokerLevel::HighCard,
         }
     }
 }
 
-// TODO: Use &str and add lifetimes
-#[derive(PartialEq, Debug, Eq)]
-struct CamelPokerHand {
-    hand: Vec<CardRank>,
-    level: PokerLevel,
-}
+/// CardRank is the strength of a card under the rules a hand is played with. When two hands have
+/// the same level, the first card with a higher rank wins.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Copy, Clone)]
+struct CardRank(u8);
 
-enum RuleSet {
-    Simple,
-    WithJokers,
-}
+/// A rank takes 4 bits of a hand's sort key, so a deck can have at most this many cards.
+const MAX_CARDS: usize = 16;
 
-impl CamelPokerHand {
-    fn new_with_jokers(hand: String) -> Self {
-        if hand.len() != 5 {
-            panic!("poker hand must have 5 cards, found {}", hand.len())
-        }
-        let mut freq: HashMap<char, u8> = HashMap::new();
+/// Rules decide how strong each card is and how a hand is classified, so that variants of Camel
+/// Cards only describe what they change.
+trait Rules {
+    /// Returns every card from the weakest to the strongest, at most MAX_CARDS of them.
+    fn card_order(&self) -> &str;
 
-        let mut cards = vec![];
+    /// Returns the cards that count as whichever card makes the hand strongest.
+    fn wild_cards(&self) -> &str {
+        ""
+    }
+
+    fn rank(&self, card: char) -> Option<CardRank> {
+        self.card_order().chars().position(|c| c == card).map(|idx| CardRank(idx as u8))
+    }
+
+    /// Groups the matching cards of the hand and classifies it by its two largest groups. Wild
+    /// cards all join the largest group, which is always the strongest choice: it turns a pair
+    /// into three of a kind before it could turn two pairs into a full house.
+    fn classify(&self, hand: &str) -> PokerLevel {
+        let mut counts = [0u8; MAX_CARDS];
+        let mut wild = 0;
         for card in hand.chars() {
-            let card_count = freq.entry(card).or_default();
-            *card_count += 1;
-            cards.push(CardRank::parse_rank(card.to_string(), WithJokers));
+            if self.wild_cards().contains(card) {
+                wild += 1;
+                continue;
+            }
+            let rank = self.rank(card).unwrap_or_else(|| panic!("invalid value for card {card}"));
+            counts[rank.0 as usize] += 1;
         }
+        let (mut largest, mut second) = (0, 0);
+        for count in counts {
+            if count > largest {
+                (largest, second) = (count, largest);
+            } else if count > second {
+                second = count;
+            }
+        }
+        PokerLevel::from_groups(largest + wild, second)
+    }
 
-        let num_jokers =  freq.remove(&'J').unwrap_or_default();
-
-        if num_jokers == 5 {
-            return Self{hand: cards, level: PokerLevel::FiveOfAKind}
+    /// Returns the card the wild cards of the hand are played as, or None if it has none. That
+    /// is the card of the largest group, the stronger one on ties, or the strongest card of the
+    /// deck when every card is wild.
+    fn wild_substitute(&self, hand: &str) -> Option<char> {
+        if !hand.chars().any(|c| self.wild_cards().contains(c)) {
+            return None;
         }
+        let natural = |c: &char| !self.wild_cards().contains(*c);
+        hand.chars()
+            .filter(natural)
+            .max_by_key(|&c| (hand.matches(c).count(), self.rank(c)))
+            .or_else(|| self.card_order().chars().filter(natural).last())
+    }
+}
 
-        let mut values = freq.values().collect::<Vec<_>>();
-        values.sort();
-        values.reverse();
-
-        let top_card = *values[0] + num_jokers;
-
-        let level = if top_card == 5 {
-            PokerLevel::FiveOfAKind
-        } else if top_card == 4 {
-            PokerLevel::FourOfAKind
-        } else if top_card == 3 && *values[1] == 2 {
-            PokerLevel::FullHouse
-        } else if top_card == 3 && *values[1] == 1 {
-            PokerLevel::ThreeOfAKind
-        } else if top_card == 2 && *values[1] == 2 {
-            PokerLevel::TwoPair
-        } else if top_card == 2 && *values[1] == 1 {
-            PokerLevel::OnePair
-        } else {
-            PokerLevel::HighCard
-        };
+/// Standard rules from part 1: J is a jack.
+struct Standard;
 
-        Self { hand: cards, level }
+impl Rules for Standard {
+    fn card_order(&self) -> &str {
+        "23456789TJQKA"
     }
-    fn new_simple(hand: String) -> Self {
-        if hand.len() != 5 {
-            panic!("poker hand must have 5 cards, found {}", hand.len())
-        }
-        let mut freq: HashMap<char, u8> = HashMap::new();
-
-        let mut cards = vec![];
-        for card in hand.chars() {
-            let card_count = freq.entry(card).or_default();
-            *card_count += 1;
-            cards.push(CardRank::parse_rank(card.to_string(), Simple));
-        }
+}
 
-        let mut values = freq.values().collect::<Vec<_>>();
-        values.sort();
-        values.reverse();
-
-        let level = if *values[0] == 5 {
-            PokerLevel::FiveOfAKind
-        } else if *values[0] == 4 {
-            PokerLevel::FourOfAKind
-        } else if *values[0] == 3 && *values[1] == 2 {
-            PokerLevel::FullHouse
-        } else if *values[0] == 3 && *values[1] == 1 {
-            PokerLevel::ThreeOfAKind
-        } else if *values[0] == 2 && *values[1] == 2 {
-            PokerLevel::TwoPair
-        } else if *values[0] == 2 && *values[1] == 1 {
-            PokerLevel::OnePair
-        } else {
-            PokerLevel::HighCard
-        };
+/// Part 2 rules: J is a joker, which is wild but the weakest card when breaking ties.
+struct JokersWild;
 
-        Self { hand: cards, level }
+impl Rules for JokersWild {
+    fn card_order(&self) -> &str {
+        "J23456789TQKA"
     }
 
-    fn new_with_rules(hand: String, rule_set: RuleSet) -> Self {
-        match rule_set {
-            Simple => Self::new_simple(hand),
-            WithJokers => Self::new_with_jokers(hand),
-        }
+    fn wild_cards(&self) -> &str {
+        "J"
     }
+}
 
-    fn new(hand: String) -> Self {
-        Self::new_simple(hand)
+#[derive(PartialEq, Debug, Eq, Copy, Clone)]
+struct CamelPokerHand {
+    cards: [CardRank; 5],
+    level: PokerLevel,
+    /// The level followed by the rank of each card, 4 bits apiece, so that comparing the keys of
+    /// two hands compares the hands.
+    sort_key: u32,
+}
+
+impl CamelPokerHand {
+    /// Reads a hand under the given rules. The hand must have been checked by `parse_round`.
+    fn new(hand: &str, rules: &impl Rules) -> Self {
+        let mut cards = [CardRank(0); 5];
+        for (slot, card) in cards.iter_mut().zip(hand.chars()) {
+            *slot = rules.rank(card).unwrap_or_else(|| panic!("invalid value for card {card}"));
+            assert!((slot.0 as usize) < MAX_CARDS, "the deck has more than {MAX_CARDS} cards");
+        }
+        let level = rules.classify(hand);
+        let sort_key = cards.iter().fold(level as u32, |key, card| key << 4 | card.0 as u32);
+        Self { cards, level, sort_key }
     }
 }
 
-struct CamelPokerRound {
+struct CamelPokerRound<'a> {
+    text: &'a str,
     hand: CamelPokerHand,
     bid: usize,
 }
 
 impl PartialOrd for CamelPokerHand {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        // Check the strength of the hand first
-        if self.level != other.level {
-            return self.level.partial_cmp(&other.level);
-        }
-        // On ties, go by each
-        for (self_card, other_card) in zip(self.hand.clone(), other.hand.clone()) {
-            let cmp = self_card.partial_cmp(&other_card);
-            if cmp != Some(Ordering::Equal) {
-                return cmp;
-            }
-        }
-        Some(Ordering::Equal)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for CamelPokerHand {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self == other {
-            return Ordering::Equal;
-        }
-        self.partial_cmp(other).unwrap()
+        self.sort_key.cmp(&other.sort_key)
     }
 }
@@ -1,37 +1,438 @@
+use std::collections::{HashMap, HashSet};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::char;
+use nom::combinator::value;
+use nom::IResult;
+use nom::multi::many1;
+use nom::sequence::{delimited, separated_pair};
+use crate::solver::{Answer, Solver, SolveError};
+use crate::utils::parsers::parse_line;
+use crate::registry::SolverRegistry;
 
-/*
---- Day 8: Haunted Wasteland ---
+pub struct Day8Solver {}
 
-You're still riding a camel across Desert Island when you spot a sandstorm quickly approaching. When you turn to warn the Elf, she disappears before your eyes! To be fair, she had just finished warning you about ghosts a few minutes ago.
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    registry.register(2023, 8, Box::new(Day8Solver {}));
+}
 
-One of the camel's pouches is labeled "maps" - sure enough, it's full of documents (your puzzle input) about how to navigate the desert. At least, you're pretty sure that's what they are; one of the documents contains a list of left/right instructions, and the rest of the documents seem to describe some kind of network of labeled nodes.
+impl Solver for Day8Solver {
+    type Input = Network;
 
-It seems like you're meant to use the left/right instructions to navigate the network. Perhaps if you have the camel follow the same instructions, you can escape the haunted wasteland!
+    fn parse(&self, input: &str) -> Result<Self::Input, SolveError> {
+        network_from_lines(input)
+    }
 
-After examining the maps for a bit, two nodes stick out: AAA and ZZZ. You feel like AAA is where you are now, and you have to follow the left/right instructions until you reach ZZZ.
+    fn solve_part_1(&self, network: &Self::Input) -> Result<Answer, SolveError> {
+        let start = network
+            .node_id("AAA")
+            .ok_or_else(|| SolveError::new("network has no AAA node"))?;
+        network
+            .steps_to_exit(start, |name| name == "ZZZ")
+            .map(Answer::from)
+            .ok_or_else(|| SolveError::new("ZZZ can't be reached from AAA"))
+    }
 
-This format defines each node of the network individually. For example:
+    fn solve_part_2(&self, network: &Self::Input) -> Result<Answer, SolveError> {
+        let starts = network.node_ids_matching(|name| name.ends_with('A'));
+        if starts.is_empty() {
+            return Err(SolveError::new("network has no node ending in A"));
+        }
+        let ghosts = starts
+            .into_iter()
+            .map(|start| network.ghost_cycle(start, |name| name.ends_with('Z')))
+            .collect::<Vec<_>>();
+        steps_until_all_ghosts_exit(&ghosts)?
+            .map(Answer::from)
+            .ok_or_else(|| SolveError::new("ghosts never stand on exits at the same time"))
+    }
+}
 
-RL
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Direction {
+    Left,
+    Right,
+}
 
-AAA = (BBB, CCC)
-BBB = (DDD, EEE)
-CCC = (ZZZ, GGG)
-DDD = (DDD, DDD)
-EEE = (EEE, EEE)
-GGG = (GGG, GGG)
-ZZZ = (ZZZ, ZZZ)
+#[derive(Debug)]
+pub struct Network {
+    instructions: Vec<Direction>,
+    names: Vec<String>,
+    // Each node is stored by index. The entry holds the index of the left and right neighbours.
+    edges: Vec<(usize, usize)>,
+}
 
-Starting with AAA, you need to look up the next element based on the next left/right instruction in your input. In this example, start with AAA and go right (R) by choosing the right element of AAA, CCC. Then, L means to choose the left element of CCC, ZZZ. By following the left/right instructions, you reach ZZZ in 2 steps.
+impl Network {
+    fn node_id(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
 
-Of course, you might not find ZZZ right away. If you run out of left/right instructions, repeat the whole sequence of instructions as necessary: RL really means RLRLRLRLRLRLRLRL... and so on. For example, here is a situation that takes 6 steps to reach ZZZ:
+    fn node_ids_matching(&self, predicate: impl Fn(&str) -> bool) -> Vec<usize> {
+        (0..self.names.len()).filter(|&id| predicate(&self.names[id])).collect()
+    }
 
-LLR
+    fn next(&self, node: usize, step: usize) -> usize {
+        let (left, right) = self.edges[node];
+        match self.instructions[step % self.instructions.len()] {
+            Direction::Left => left,
+            Direction::Right => right,
+        }
+    }
 
-AAA = (BBB, BBB)
-BBB = (AAA, ZZZ)
-ZZZ = (ZZZ, ZZZ)
+    /// Counts the steps from `start` to the first exit, or returns None if the walk comes back
+    /// to the same (node, instruction index) state without passing one, since it would then loop
+    /// forever.
+    fn steps_to_exit(&self, start: usize, is_exit: impl Fn(&str) -> bool) -> Option<usize> {
+        let mut seen = HashSet::new();
+        let mut node = start;
+        let mut steps = 0;
+        while !is_exit(&self.names[node]) {
+            if !seen.insert((node, steps % self.instructions.len())) {
+                return None;
+            }
+            node = self.next(node, steps);
+            steps += 1;
+        }
+        Some(steps)
+    }
 
-Starting at AAA, follow the left/right instructions. How many steps are required to reach ZZZ?
+    /// Walks from `start` until the same (node, instruction index) state is seen twice. Every
+    /// walk is eventually periodic because there are finitely many states, so the ghost's exits
+    /// can be described by the exits it passes before the cycle and the ones inside the cycle.
+    fn ghost_cycle(&self, start: usize, is_exit: impl Fn(&str) -> bool) -> GhostCycle {
+        let mut seen: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut exits = vec![];
+        let mut node = start;
+        let mut steps = 0;
+        loop {
+            let state = (node, steps % self.instructions.len());
+            if let Some(&cycle_start) = seen.get(&state) {
+                return GhostCycle {
+                    cycle_start,
+                    cycle_length: steps - cycle_start,
+                    exits,
+                };
+            }
+            seen.insert(state, steps);
+            if is_exit(&self.names[node]) {
+                exits.push(steps);
+            }
+            node = self.next(node, steps);
+            steps += 1;
+        }
+    }
+}
 
-*/
\ No newline at end of file
+/// GhostCycle describes every step count at which a single ghost stands on an exit. Exits before
+/// `cycle_start` happen exactly once, exits at or after it repeat every `cycle_length` steps.
+#[derive(Debug, Clone, PartialEq)]
+struct GhostCycle {
+    cycle_start: usize,
+    cycle_length: usize,
+    exits: Vec<usize>,
+}
+
+impl GhostCycle {
+    fn is_exit_at(&self, step: usize) -> bool {
+        if step < self.cycle_start {
+            return self.exits.contains(&step);
+        }
+        let offset = (step - self.cycle_start) % self.cycle_length;
+        self.exits.contains(&(self.cycle_start + offset))
+    }
+
+    fn periodic_exits(&self) -> impl Iterator<Item=&usize> {
+        self.exits.iter().filter(|&&e| e >= self.cycle_start)
+    }
+}
+
+/// Periodic is the set of steps t >= min_step where t = residue mod modulus.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Periodic {
+    residue: u64,
+    modulus: u64,
+    min_step: u64,
+}
+
+impl Periodic {
+    /// Returns the first step of the set, or an error if it is past u64::MAX.
+    fn first(&self) -> Result<u64, SolveError> {
+        if self.residue >= self.min_step {
+            return Ok(self.residue);
+        }
+        let cycles = (self.min_step - self.residue).div_ceil(self.modulus);
+        cycles
+            .checked_mul(self.modulus)
+            .and_then(|offset| offset.checked_add(self.residue))
+            .ok_or_else(|| SolveError::new(format!("the ghosts first meet on exits past step {}", u64::MAX)))
+    }
+
+    /// Combines two periodic constraints with the Chinese remainder theorem. When the moduli are
+    /// coprime-ish and every residue is zero this is just the LCM of the cycle lengths. Returns
+    /// None if the constraints never line up, and an error if the combined period doesn't fit
+    /// in a u64.
+    fn combine(&self, other: &Periodic) -> Result<Option<Periodic>, SolveError> {
+        let (g, p, _) = extended_gcd(self.modulus as i128, other.modulus as i128);
+        let diff = other.residue as i128 - self.residue as i128;
+        if diff % g != 0 {
+            return Ok(None);
+        }
+        let modulus = (self.modulus as i128 / g)
+            .checked_mul(other.modulus as i128)
+            .and_then(|lcm| u64::try_from(lcm).ok())
+            .ok_or_else(|| {
+                SolveError::new(format!(
+                    "the combined cycle of lengths {} and {} is longer than {} steps",
+                    self.modulus,
+                    other.modulus,
+                    u64::MAX
+                ))
+            })?;
+        let lcm = modulus as i128;
+        let step = (other.modulus as i128) / g;
+        // Both factors are below `step`, so their product fits in a u128 but not always in an
+        // i128. k is below `step` again, so k * self.modulus stays below the lcm.
+        let k = (diff / g).rem_euclid(step) as u128 * p.rem_euclid(step) as u128 % step as u128;
+        let k = k as i128;
+        let residue = (self.residue as i128 + k * self.modulus as i128).rem_euclid(lcm);
+        Ok(Some(Periodic {
+            // The residue is below the modulus, so it fits too.
+            residue: residue as u64,
+            modulus,
+            min_step: self.min_step.max(other.min_step),
+        }))
+    }
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (g, x, y) = extended_gcd(b, a % b);
+    (g, y, x - (a / b) * y)
+}
+
+fn steps_until_all_ghosts_exit(ghosts: &[GhostCycle]) -> Result<Option<u64>, SolveError> {
+    // Exits that happen before a ghost enters its cycle only happen once, so check them directly.
+    let mut best = ghosts
+        .iter()
+        .flat_map(|g| g.exits.iter().filter(|&&e| e < g.cycle_start))
+        .filter(|&&step| ghosts.iter().all(|g| g.is_exit_at(step)))
+        .min()
+        .map(|&step| step as u64);
+
+    // Otherwise combine the periodic exits of every ghost.
+    let mut candidates = vec![Periodic { residue: 0, modulus: 1, min_step: 0 }];
+    for ghost in ghosts {
+        let mut next = vec![];
+        for candidate in candidates.iter() {
+            for &exit in ghost.periodic_exits() {
+                let constraint = Periodic {
+                    residue: (exit % ghost.cycle_length) as u64,
+                    modulus: ghost.cycle_length as u64,
+                    min_step: ghost.cycle_start as u64,
+                };
+                next.extend(candidate.combine(&constraint)?);
+            }
+        }
+        candidates = next;
+    }
+    for candidate in candidates {
+        let step = candidate.first()?;
+        if best.is_none_or(|b| step < b) {
+            best = Some(step);
+        }
+    }
+    Ok(best)
+}
+
+fn network_from_lines(input: &str) -> Result<Network, SolveError> {
+    let first_line = input.lines().next().unwrap_or_default();
+    let instructions = parse_line(0, first_line, "parsing instructions", parse_instructions)?;
+    if let Some(line) = input.lines().nth(1).filter(|line| !line.trim().is_empty()) {
+        return Err(SolveError::new(format!("expected a blank line after the instructions, found {line:?}"))
+            .with_line(1)
+            .with_context("parsing instructions"));
+    }
+    let mut nodes = vec![];
+    for (idx, line) in input.lines().enumerate().skip(2) {
+        if line.is_empty() {
+            continue;
+        }
+        nodes.push(parse_line(idx, line, "parsing node", parse_node)?);
+    }
+
+    let names = nodes.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>();
+    let ids: HashMap<&str, usize> = nodes.iter().enumerate().map(|(id, (name, _))| (*name, id)).collect();
+    let node_id = |name: &str| {
+        ids.get(name)
+            .copied()
+            .ok_or_else(|| SolveError::new(format!("node {name} is never defined")))
+    };
+    let mut edges = Vec::with_capacity(nodes.len());
+    for (_, (left, right)) in nodes.iter() {
+        edges.push((node_id(left)?, node_id(right)?));
+    }
+    Ok(Network {
+        instructions,
+        names,
+        edges,
+    })
+}
+
+fn parse_instructions(line: &str) -> IResult<&str, Vec<Direction>> {
+    many1(alt((
+        value(Direction::Left, char('L')),
+        value(Direction::Right, char('R')),
+    )))(line)
+}
+
+fn parse_node_name(i: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_ascii_alphanumeric())(i)
+}
+
+fn parse_node(line: &str) -> IResult<&str, (&str, (&str, &str))> {
+    separated_pair(
+        parse_node_name,
+        tag(" = "),
+        delimited(
+            char('('),
+            separated_pair(parse_node_name, tag(", "), parse_node_name),
+            char(')'),
+        ),
+    )(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Part;
+    use crate::test_support::{example, require_input};
+
+    #[test]
+    fn test_part_1_unit() {
+        let solver = Day8Solver {};
+        let input = solver.parse(&example(2023, 8, Part::One)).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_part_1_repeating_instructions() {
+        let solver = Day8Solver {};
+        let input = solver.parse(&[
+            "LLR",
+            "",
+            "AAA = (BBB, BBB)",
+            "BBB = (AAA, ZZZ)",
+            "ZZZ = (ZZZ, ZZZ)",
+        ].join("\n")).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "6");
+    }
+
+    #[test]
+    fn test_unreachable_exit() {
+        let solver = Day8Solver {};
+        let input = solver.parse(&["LR", "", "AAA = (BBB, BBB)", "BBB = (AAA, AAA)", "ZZZ = (ZZZ, ZZZ)"].join("\n")).unwrap();
+        let err = solver.solve_part_1(&input).unwrap_err();
+        assert_eq!(err.to_string(), "ZZZ can't be reached from AAA");
+        let input = solver.parse(&["L", "", "BBB = (BBZ, BBZ)", "BBZ = (BBB, BBB)"].join("\n")).unwrap();
+        let err = solver.solve_part_2(&input).unwrap_err();
+        assert_eq!(err.to_string(), "network has no node ending in A");
+    }
+
+    #[test]
+    fn test_combine_overflow() {
+        // Two coprime periods whose product is past u64::MAX.
+        let a = Periodic { residue: 0, modulus: u64::MAX, min_step: 0 };
+        let b = Periodic { residue: 0, modulus: u64::MAX - 1, min_step: 0 };
+        let err = a.combine(&b).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("the combined cycle of lengths {} and {} is longer than {} steps", u64::MAX, u64::MAX - 1, u64::MAX)
+        );
+        assert_eq!(a.combine(&a), Ok(Some(a)));
+        // Periods near u64::MAX that still combine.
+        let b = Periodic { residue: 5, modulus: 1, min_step: 0 };
+        assert_eq!(b.combine(&Periodic { residue: 7, ..a }), Ok(Some(Periodic { residue: 7, ..a })));
+        // The first step of a valid period can still be out of reach.
+        let late = Periodic { residue: 1, modulus: u64::MAX - 1, min_step: u64::MAX };
+        assert_eq!(late.first(), Ok(u64::MAX));
+        assert!(Periodic { residue: 2, ..late }.first().is_err());
+    }
+
+    #[test]
+    fn test_part_2_unit() {
+        let solver = Day8Solver {};
+        let input = solver.parse(&example(2023, 8, Part::Two)).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "6");
+    }
+
+    #[test]
+    fn test_missing_blank_line() {
+        let solver = Day8Solver {};
+        let err = solver.parse(&["LR", "AAA = (ZZZ, ZZZ)", "ZZZ = (ZZZ, ZZZ)"].join("\n")).unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.context.as_deref(), Some("parsing instructions"));
+    }
+
+    #[test]
+    fn test_undefined_node() {
+        let solver = Day8Solver {};
+        let err = solver.parse(&["LR", "", "AAA = (BBB, ZZZ)", "ZZZ = (ZZZ, ZZZ)"].join("\n")).unwrap_err();
+        assert_eq!(err.to_string(), "node BBB is never defined");
+        let err = solver.parse(&["LR", "", "AAA = (BBB ZZZ)"].join("\n")).unwrap_err();
+        assert_eq!(err.line, Some(3));
+    }
+
+    #[test]
+    fn test_ghost_cycle() {
+        let network = network_from_lines(&[
+            "LR",
+            "",
+            "11A = (11B, XXX)",
+            "11B = (XXX, 11Z)",
+            "11Z = (11B, XXX)",
+            "XXX = (XXX, XXX)",
+        ].join("\n")).unwrap();
+        let cycle = network.ghost_cycle(network.node_id("11A").unwrap(), |name| name.ends_with('Z'));
+        assert_eq!(cycle, GhostCycle { cycle_start: 1, cycle_length: 2, exits: vec![2] });
+        assert!(cycle.is_exit_at(4));
+        assert!(!cycle.is_exit_at(5));
+    }
+
+    #[test]
+    fn test_ghosts_with_offset_cycles() {
+        // Exits at 3, 8, 13, ... and at 4, 8, 12, ... first meet at 8.
+        let ghosts = vec![
+            GhostCycle { cycle_start: 0, cycle_length: 5, exits: vec![3] },
+            GhostCycle { cycle_start: 0, cycle_length: 4, exits: vec![0] },
+        ];
+        assert_eq!(steps_until_all_ghosts_exit(&ghosts), Ok(Some(8)));
+        // Both ghosts are on an exit before either of them is in a cycle.
+        let ghosts = vec![
+            GhostCycle { cycle_start: 3, cycle_length: 5, exits: vec![2, 4] },
+            GhostCycle { cycle_start: 4, cycle_length: 4, exits: vec![2] },
+        ];
+        assert_eq!(steps_until_all_ghosts_exit(&ghosts), Ok(Some(2)));
+        // Residues that can never line up.
+        let ghosts = vec![
+            GhostCycle { cycle_start: 0, cycle_length: 2, exits: vec![0] },
+            GhostCycle { cycle_start: 0, cycle_length: 4, exits: vec![1] },
+        ];
+        assert_eq!(steps_until_all_ghosts_exit(&ghosts), Ok(None));
+    }
+
+    #[test]
+    fn test_part_1() {
+        let solver = Day8Solver {};
+        let input = solver.parse(&require_input!(2023, 8)).unwrap();
+        assert_eq!(solver.solve_part_1(&input).unwrap(), "24253");
+    }
+
+    #[test]
+    fn test_part_2() {
+        let solver = Day8Solver {};
+        let input = solver.parse(&require_input!(2023, 8)).unwrap();
+        assert_eq!(solver.solve_part_2(&input).unwrap(), "12357789728873");
+    }
+}
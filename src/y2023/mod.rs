@@ -1,7 +1,23 @@
+use crate::registry::SolverRegistry;
+
 pub mod day01;
 pub mod day02;
 pub mod day03;
 pub mod day04;
 pub mod day05;
 pub(crate) mod day06;
-pub(crate) mod day07;
\ No newline at end of file
+pub(crate) mod day07;
+pub(crate) mod day08;
+
+/// Adds every 2023 day to the registry. A new day only needs its module declared above and a
+/// call to its `register` function here.
+pub(crate) fn register(registry: &mut SolverRegistry) {
+    day01::register(registry);
+    day02::register(registry);
+    day03::register(registry);
+    day04::register(registry);
+    day05::register(registry);
+    day06::register(registry);
+    day07::register(registry);
+    day08::register(registry);
+}